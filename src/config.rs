@@ -1,38 +1,1402 @@
-use serde::Deserialize;
-use std::path::Path;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize, Clone)]
+use anyhow::Context;
+use toml_edit::{ImDocument, TableLike};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     /// Refresh interval in minutes
     #[serde(default = "default_refresh_interval")]
     pub refresh_interval: u64,
+    /// SQLite connection tuning
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    /// Further files with `[[groups]]` and `[[feeds]]`, relative to this one.
+    /// Wildcards (`*`, `?`) are allowed in the file name.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Dashboard sections that feeds can be filed under
+    #[serde(default)]
+    pub groups: Vec<GroupConfig>,
+    /// Dashboard-wide highlight and mute lists
+    #[serde(default)]
+    pub keywords: KeywordsConfig,
+    /// Title, logo and other branding shown on every page
+    #[serde(default)]
+    pub site: SiteConfig,
+    /// Proxy feeds are fetched through; reqwest's `*_PROXY` environment variables when unset
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Clean-up of entry links before they are stored
+    #[serde(default)]
+    pub links: LinksConfig,
+    /// Sign-in for mobile and desktop reader apps; their APIs are disabled when unset
+    #[serde(default)]
+    pub reader_api: Option<ReaderApiConfig>,
     pub feeds: Vec<FeedConfig>,
+    /// Feeds marked `disabled`, kept out of `feeds` so nothing fetches or shows them
+    #[serde(skip)]
+    pub disabled_feeds: Vec<FeedConfig>,
 }
 
 fn default_refresh_interval() -> u64 {
     15
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            refresh_interval: default_refresh_interval(),
+            database: DatabaseConfig::default(),
+            include: Vec::new(),
+            groups: Vec::new(),
+            keywords: KeywordsConfig::default(),
+            site: SiteConfig::default(),
+            proxy: None,
+            links: LinksConfig::default(),
+            reader_api: None,
+            feeds: Vec::new(),
+            disabled_feeds: Vec::new(),
+        }
+    }
+}
+
+/// What a file named by `include` may contain
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct ConfigFragment {
+    #[serde(default)]
+    groups: Vec<GroupConfig>,
+    #[serde(default)]
+    feeds: Vec<FeedConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DatabaseConfig {
+    /// Maximum number of pooled connections
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    /// How long a connection waits on a locked database, in milliseconds
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64,
+    /// SQLite `journal_mode` pragma (e.g. "wal", "delete")
+    #[serde(default = "default_journal_mode")]
+    pub journal_mode: String,
+    /// SQLite `synchronous` pragma (e.g. "normal", "full")
+    #[serde(default = "default_synchronous")]
+    pub synchronous: String,
+}
+
+fn default_max_connections() -> u32 {
+    5
+}
+
+fn default_busy_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_journal_mode() -> String {
+    "wal".to_string()
+}
+
+fn default_synchronous() -> String {
+    "normal".to_string()
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: default_max_connections(),
+            busy_timeout_ms: default_busy_timeout_ms(),
+            journal_mode: default_journal_mode(),
+            synchronous: default_synchronous(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GroupConfig {
+    pub name: String,
+    /// Position among groups; ties keep their order in the file
+    #[serde(default)]
+    pub order: i64,
+    /// Start with the group's section collapsed on the dashboard
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+impl GroupConfig {
+    pub fn slug(&self) -> String {
+        slugify(&self.name)
+    }
+}
+
+/// URL-friendly form of a name: lowercase letters and digits joined by dashes
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct FeedConfig {
     pub name: String,
     pub url: String,
     #[serde(default)]
     pub has_discussion: bool,
+    /// Name of the group the feed is shown under
+    #[serde(default)]
+    pub group: Option<String>,
+    /// When non-empty, only entries matching at least one of these are stored
+    #[serde(default)]
+    pub include: Vec<FilterRule>,
+    /// Entries matching any of these are dropped before they are stored
+    #[serde(default)]
+    pub exclude: Vec<FilterRule>,
+    /// Regex substitutions applied in order to entry titles before they are stored
+    #[serde(default)]
+    pub title_rewrites: Vec<TitleRewrite>,
+    /// Trim titles and collapse runs of whitespace, including newlines, to one space
+    #[serde(default)]
+    pub collapse_whitespace: bool,
+    /// Remove a trailing " | Site Name" (or " - ", " — ", ...) naming the feed or its site
+    #[serde(default)]
+    pub strip_site_suffix: bool,
+    /// Items shown at first and per "Show More"; defaults to `DEFAULT_ITEMS_PER_PAGE`
+    #[serde(default)]
+    pub items_per_page: Option<u32>,
+    /// Hide items published more than this many days ago
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// Column accent color as `#rgb` or `#rrggbb`
+    #[serde(default)]
+    pub accent: Option<String>,
+    /// How much of the dashboard row the column takes
+    #[serde(default)]
+    pub width: ColumnWidth,
+    /// Start with the column's items hidden
+    #[serde(default)]
+    pub collapsed: bool,
+    /// Extra headers sent when fetching the feed
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Credentials sent when fetching the feed
+    #[serde(default)]
+    pub auth: Option<FeedAuth>,
+    /// Replaces the default `User-Agent` for this feed
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Request timeout for this feed; defaults to `DEFAULT_TIMEOUT_SECS`
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Proxy URL for this feed instead of `[proxy]`, or `DIRECT_PROXY` to use none
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Keep the feed in the file but neither fetch nor show it
+    #[serde(default)]
+    pub disabled: bool,
+    /// File the feed was defined in; set by `Config::load`
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    /// `url` before environment variables were expanded, when it had any
+    #[serde(skip)]
+    pub url_template: Option<String>,
+}
+
+/// Items per page for feeds that don't set `items_per_page`
+pub const DEFAULT_ITEMS_PER_PAGE: u32 = 15;
+
+/// Upper bound on `items_per_page`, to keep the dashboard reasonably sized
+pub const MAX_ITEMS_PER_PAGE: u32 = 100;
+
+/// Request timeout for feeds that don't set `timeout_secs`
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Credentials for a feed; either `bearer`, or `username` with an optional `password`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedAuth {
+    #[serde(default)]
+    pub bearer: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Width hint for a feed's dashboard column
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnWidth {
+    #[default]
+    Normal,
+    /// Two grid columns
+    Wide,
+    /// The whole row
+    Full,
+}
+
+/// What part of an entry a filter rule looks at
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterField {
+    Title,
+    /// Host name of the entry's link; keywords also match subdomains
+    Domain,
+    Author,
+    Category,
+}
+
+/// A filter rule; exactly one of `keyword` (case-insensitive substring) or `regex` is set
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FilterRule {
+    pub field: FilterField,
+    #[serde(default)]
+    pub keyword: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+}
+
+/// A regex substitution for entry titles; `replace` may refer to groups as `$1` or `${name}`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TitleRewrite {
+    pub regex: String,
+    #[serde(default)]
+    pub replace: String,
+}
+
+/// Title matches applied when items are shown, not when they are fetched
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct KeywordsConfig {
+    /// Items whose title matches any of these are highlighted
+    #[serde(default)]
+    pub highlight: Vec<KeywordRule>,
+    /// Items whose title matches any of these are collapsed
+    #[serde(default)]
+    pub mute: Vec<KeywordRule>,
+}
+
+/// Exactly one of `keyword` (case-insensitive substring) or `regex` is set
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeywordRule {
+    #[serde(default)]
+    pub keyword: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+}
+
+/// Outbound proxy for feed fetching
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProxyConfig {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` URL, optionally with credentials
+    pub url: String,
+    /// Hosts fetched directly: domains (with their subdomains), IP addresses or CIDR ranges
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+/// Link clean-up applied to the entries of every feed
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LinksConfig {
+    /// Query parameters removed from links, ignoring case; a trailing `*` matches
+    /// any ending, as in `utm_*`
+    #[serde(default)]
+    pub strip_params: Vec<String>,
+    /// Per-domain rewrites; the first rule whose domain matches a link applies
+    #[serde(default)]
+    pub rewrites: Vec<LinkRewrite>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkRewrite {
+    /// Host the rule applies to, along with its subdomains
+    pub domain: String,
+    /// Switch `http:` links to `https:`
+    #[serde(default)]
+    pub https: bool,
+    /// Host to send links to instead, e.g. a privacy-friendly front-end
+    #[serde(default)]
+    pub host: Option<String>,
+}
+
+/// Credentials reader apps sign in with
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReaderApiConfig {
+    pub username: String,
+    pub password: String,
+}
+
+/// Value of a feed's `proxy` that fetches it without any proxy
+pub const DIRECT_PROXY: &str = "direct";
+
+/// Schemes a proxy URL may use
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// Branding for an instance; the defaults are Moar News' own
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SiteConfig {
+    /// Shown in the header and page titles
+    #[serde(default = "default_site_title")]
+    pub title: String,
+    /// Shown next to the title on the dashboard
+    #[serde(default)]
+    pub tagline: Option<String>,
+    /// Inline `<svg>` markup for the header logo
+    #[serde(default)]
+    pub logo_svg: Option<String>,
+    /// Image URL for the header logo, instead of `logo_svg`
+    #[serde(default)]
+    pub logo_url: Option<String>,
+    /// Favicon URL; the built-in icon when unset
+    #[serde(default)]
+    pub favicon: Option<String>,
+    /// Links in the page footer
+    #[serde(default = "default_footer_links")]
+    pub footer_links: Vec<FooterLink>,
+    /// Extra stylesheets loaded after the built-in one, e.g. `/static/custom.css`
+    #[serde(default)]
+    pub custom_css: Vec<String>,
+    /// Extra scripts loaded on every page
+    #[serde(default)]
+    pub custom_js: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FooterLink {
+    pub label: String,
+    pub url: String,
+}
+
+fn default_site_title() -> String {
+    "Moar News".to_string()
+}
+
+fn default_footer_links() -> Vec<FooterLink> {
+    vec![FooterLink {
+        label: "View Source".to_string(),
+        url: "https://github.com/laydros/moar-news".to_string(),
+    }]
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        Self {
+            title: default_site_title(),
+            tagline: None,
+            logo_svg: None,
+            logo_url: None,
+            favicon: None,
+            footer_links: default_footer_links(),
+            custom_css: Vec::new(),
+            custom_js: Vec::new(),
+        }
+    }
 }
 
 impl Config {
+    /// Load `path` and the files it includes, in order: the main file's feeds
+    /// first, then each `include` pattern's files sorted by name.
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        let path = path.as_ref();
+        let content = read_config_file(path)?;
+        let mut config = Self::parse(&content).map_err(|e| e.with_path(path))?;
+        for feed in &mut config.feeds {
+            feed.source = Some(path.to_path_buf());
+        }
+
+        // Where each name, URL and group slug was first defined
+        let mut names: HashMap<String, PathBuf> = HashMap::new();
+        let mut urls: HashMap<String, PathBuf> = HashMap::new();
+        let mut slugs: HashMap<String, PathBuf> = HashMap::new();
+        for feed in &config.feeds {
+            names.insert(feed.name.clone(), path.to_path_buf());
+            urls.insert(feed.url.clone(), path.to_path_buf());
+        }
+        for group in &config.groups {
+            slugs.insert(group.slug(), path.to_path_buf());
+        }
+
+        let mut problems = Vec::new();
+        for file in config.include_files(path)? {
+            let content = read_config_file(&file)?;
+            let fragment = match Self::parse_fragment(&content) {
+                Ok(fragment) => fragment,
+                Err(e) => {
+                    problems.extend(e.problems.into_iter().map(|p| p.in_file(&file)));
+                    continue;
+                }
+            };
+
+            let doc = ImDocument::parse(content.as_str())
+                .expect("fragment was already parsed successfully");
+            let mut duplicate = |span: Option<Range<usize>>, message: String| {
+                problems.push(ConfigProblem::new(&content, span, message).in_file(&file));
+            };
+
+            for (i, group) in fragment.groups.iter().enumerate() {
+                if let Some(first) = slugs.get(&group.slug()) {
+                    duplicate(
+                        locate(&doc, &Segment::field("groups", i, "name")).value,
+                        format!("group `{}` is already defined in {}", group.name, first.display()),
+                    );
+                } else {
+                    slugs.insert(group.slug(), file.clone());
+                }
+            }
+            for (i, feed) in fragment.feeds.iter().enumerate() {
+                if let Some(first) = names.get(&feed.name) {
+                    duplicate(
+                        locate(&doc, &Segment::feed_field(i, "name")).value,
+                        format!(
+                            "duplicate feed name `{}` (also used in {})",
+                            feed.name,
+                            first.display()
+                        ),
+                    );
+                } else {
+                    names.insert(feed.name.clone(), file.clone());
+                }
+                if let Some(first) = urls.get(&feed.url) {
+                    duplicate(
+                        locate(&doc, &Segment::feed_field(i, "url")).value,
+                        format!(
+                            "duplicate feed URL `{}` (also used in {})",
+                            feed.url,
+                            first.display()
+                        ),
+                    );
+                } else {
+                    urls.insert(feed.url.clone(), file.clone());
+                }
+            }
+
+            config.groups.extend(fragment.groups);
+            config.feeds.extend(fragment.feeds.into_iter().map(|feed| FeedConfig {
+                source: Some(file.clone()),
+                ..feed
+            }));
+        }
+
+        if !problems.is_empty() {
+            return Err(ConfigError {
+                path: Some(path.to_path_buf()),
+                problems,
+            }
+            .into());
+        }
+        config.set_aside_disabled();
         Ok(config)
     }
 
+    /// The files named by `include`, for a config loaded from `config_path`.
+    /// Patterns matching nothing are skipped; a file is only listed once.
+    pub fn include_files(&self, config_path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let base = config_path.parent().unwrap_or(Path::new(""));
+        let mut files: Vec<PathBuf> = Vec::new();
+        for pattern in &self.include {
+            for file in expand_include(base, pattern)? {
+                if file != config_path && !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+        Ok(files)
+    }
+
     /// Parse config from a TOML string (useful for testing)
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(content: &str) -> anyhow::Result<Self> {
-        let config: Config = toml::from_str(content)?;
+        let mut config = Self::parse(content)?;
+        config.set_aside_disabled();
         Ok(config)
     }
+
+    /// Move disabled feeds out of `feeds`. Done after validation, so their
+    /// names and URLs still count as taken.
+    fn set_aside_disabled(&mut self) {
+        let (disabled, enabled) = std::mem::take(&mut self.feeds)
+            .into_iter()
+            .partition(|feed| feed.disabled);
+        self.feeds = enabled;
+        self.disabled_feeds = disabled;
+    }
+
+    /// Enabled feeds followed by disabled ones, as the database keeps both
+    pub fn all_feeds(&self) -> Vec<FeedConfig> {
+        self.feeds.iter().chain(&self.disabled_feeds).cloned().collect()
+    }
+
+    /// Groups in display order
+    pub fn sorted_groups(&self) -> Vec<&GroupConfig> {
+        let mut groups: Vec<&GroupConfig> = self.groups.iter().collect();
+        groups.sort_by_key(|g| g.order);
+        groups
+    }
+
+    /// Deserialize and validate, reporting every problem found
+    fn parse(content: &str) -> Result<Self, ConfigError> {
+        Self::parse_as::<Config>(content, |config| config)
+    }
+
+    /// Like `parse`, for a file named by `include`
+    fn parse_fragment(content: &str) -> Result<Self, ConfigError> {
+        Self::parse_as::<ConfigFragment>(content, |fragment| Config {
+            groups: fragment.groups,
+            feeds: fragment.feeds,
+            ..Config::default()
+        })
+    }
+
+    fn parse_as<T: DeserializeOwned>(
+        content: &str,
+        into_config: impl FnOnce(T) -> Config,
+    ) -> Result<Self, ConfigError> {
+        let doc = ImDocument::parse(content)
+            .map_err(|e| ConfigError::single(content, e.span(), e.message()))?;
+        // Deserialized from the text first so that type errors carry a location
+        toml::from_str::<T>(content)
+            .map_err(|e| ConfigError::single(content, e.span(), e.message()))?;
+
+        let mut raw: toml::Value = toml::from_str(content)
+            .map_err(|e| ConfigError::single(content, e.span(), e.message()))?;
+        let templates: Vec<Option<String>> = match raw.get("feeds") {
+            Some(toml::Value::Array(feeds)) => feeds
+                .iter()
+                .map(|feed| feed.get("url").and_then(|url| url.as_str()).map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut problems = Vec::new();
+        interpolate_env(&mut raw, &mut Vec::new(), &doc, &mut problems);
+
+        let typed: T = raw
+            .clone()
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::single(content, None, e.message()))?;
+        problems.extend(unknown_keys::<T>(&raw, &doc));
+        let mut config = into_config(typed);
+        for (feed, template) in config.feeds.iter_mut().zip(templates) {
+            feed.url_template = template.filter(|template| *template != feed.url);
+        }
+        problems.extend(config.validate(&doc));
+        if problems.is_empty() {
+            return Ok(config);
+        }
+
+        let mut problems: Vec<ConfigProblem> = problems
+            .into_iter()
+            .map(|(span, message)| ConfigProblem::new(content, span, message))
+            .collect();
+        problems.sort_by_key(|p| (p.line, p.column));
+        Err(ConfigError {
+            path: None,
+            problems,
+        })
+    }
+
+    /// Semantic checks on a deserialized config, located through `doc`
+    fn validate(&self, doc: &ImDocument<&str>) -> Vec<(Option<Range<usize>>, String)> {
+        let mut problems = Vec::new();
+
+        if self.refresh_interval == 0 {
+            problems.push((
+                locate(doc, &[Segment::key("refresh_interval")]).value,
+                "`refresh_interval` must be at least 1 minute".to_string(),
+            ));
+        }
+
+        let mut slugs: HashMap<String, usize> = HashMap::new();
+        for (i, group) in self.groups.iter().enumerate() {
+            let name_at = locate(doc, &Segment::field("groups", i, "name")).value;
+            let slug = group.slug();
+
+            if slug.is_empty() {
+                problems.push((
+                    name_at,
+                    "group name must contain at least one letter or digit".to_string(),
+                ));
+            } else if let Some(&first) = slugs.get(&slug) {
+                problems.push((
+                    name_at,
+                    format!(
+                        "group `{}` has the same URL slug `{}` as groups[{}]",
+                        group.name, slug, first
+                    ),
+                ));
+            } else {
+                slugs.insert(slug, i);
+            }
+        }
+
+        let mut names: HashMap<&str, usize> = HashMap::new();
+        let mut urls: HashMap<&str, usize> = HashMap::new();
+        for (i, feed) in self.feeds.iter().enumerate() {
+            let name_at = locate(doc, &Segment::feed_field(i, "name"));
+            let url_at = locate(doc, &Segment::feed_field(i, "url"));
+
+            if feed.name.trim().is_empty() {
+                problems.push((name_at.value.clone(), "feed name must not be empty".to_string()));
+            } else if let Some(&first) = names.get(feed.name.as_str()) {
+                problems.push((
+                    name_at.value.clone(),
+                    format!(
+                        "duplicate feed name `{}` (also used by feeds[{}])",
+                        feed.name, first
+                    ),
+                ));
+            } else {
+                names.insert(&feed.name, i);
+            }
+
+            match reqwest::Url::parse(&feed.url) {
+                Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+                Ok(url) => problems.push((
+                    url_at.value.clone(),
+                    format!(
+                        "feed `{}` uses a `{}:` URL; only http and https are supported",
+                        feed.name,
+                        url.scheme()
+                    ),
+                )),
+                Err(e) => problems.push((
+                    url_at.value.clone(),
+                    format!("feed `{}` has an invalid URL `{}`: {}", feed.name, feed.url, e),
+                )),
+            }
+
+            for (list, rules) in [("include", &feed.include), ("exclude", &feed.exclude)] {
+                for (j, rule) in rules.iter().enumerate() {
+                    let mut path = Segment::feed_field(i, list);
+                    path.push(Segment::Index(j));
+                    problems.extend(check_matcher(
+                        doc,
+                        &path,
+                        "field",
+                        rule.keyword.as_deref(),
+                        rule.regex.as_deref(),
+                        &format!("{} rule of feed `{}`", list, feed.name),
+                    ));
+                }
+            }
+
+            for (j, rewrite) in feed.title_rewrites.iter().enumerate() {
+                if let Err(e) = regex::Regex::new(&rewrite.regex) {
+                    let mut path = Segment::feed_field(i, "title_rewrites");
+                    path.extend([Segment::Index(j), Segment::key("regex")]);
+                    problems.push((
+                        locate(doc, &path).value,
+                        format!(
+                            "title rewrite of feed `{}` has an invalid regex: {}",
+                            feed.name,
+                            e.to_string().lines().last().unwrap_or_default()
+                        ),
+                    ));
+                }
+            }
+
+            if let Some(per_page) = feed.items_per_page {
+                if !(1..=MAX_ITEMS_PER_PAGE).contains(&per_page) {
+                    problems.push((
+                        locate(doc, &Segment::feed_field(i, "items_per_page")).value,
+                        format!(
+                            "feed `{}` has `items_per_page` {}; it must be between 1 and {}",
+                            feed.name, per_page, MAX_ITEMS_PER_PAGE
+                        ),
+                    ));
+                }
+            }
+
+            if feed.max_age_days == Some(0) {
+                problems.push((
+                    locate(doc, &Segment::feed_field(i, "max_age_days")).value,
+                    format!("feed `{}` has `max_age_days` 0; it must be at least 1", feed.name),
+                ));
+            }
+
+            if let Some(accent) = &feed.accent {
+                if !is_hex_color(accent) {
+                    problems.push((
+                        locate(doc, &Segment::feed_field(i, "accent")).value,
+                        format!(
+                            "feed `{}` has accent `{}`; use a hex color like `#e85a1b`",
+                            feed.name, accent
+                        ),
+                    ));
+                }
+            }
+
+            problems.extend(check_http_options(doc, i, feed));
+
+            if feed.group.as_deref().is_some_and(|g| g.trim().is_empty()) {
+                problems.push((
+                    locate(doc, &Segment::feed_field(i, "group")).value,
+                    format!("feed `{}` has an empty group name", feed.name),
+                ));
+            }
+
+            if let Some(&first) = urls.get(feed.url.as_str()) {
+                problems.push((
+                    url_at.value,
+                    format!(
+                        "duplicate feed URL `{}` (also used by feeds[{}])",
+                        feed.url, first
+                    ),
+                ));
+            } else {
+                urls.insert(&feed.url, i);
+            }
+        }
+
+        let keyword_lists = [
+            ("highlight", &self.keywords.highlight),
+            ("mute", &self.keywords.mute),
+        ];
+        for (list, rules) in keyword_lists {
+            for (j, rule) in rules.iter().enumerate() {
+                let path = vec![Segment::key("keywords"), Segment::key(list), Segment::Index(j)];
+                problems.extend(check_matcher(
+                    doc,
+                    &path,
+                    "keyword",
+                    rule.keyword.as_deref(),
+                    rule.regex.as_deref(),
+                    &format!("{} rule", list),
+                ));
+            }
+        }
+
+        problems.extend(self.site.validate(doc));
+
+        problems.extend(self.links.validate(doc));
+
+        if let Some(proxy) = &self.proxy {
+            let at = |key: &str| locate(doc, &[Segment::key("proxy"), Segment::key(key)]);
+            if let Err(message) = check_proxy_url(&proxy.url) {
+                problems.push((at("url").value, format!("proxy {}", message)));
+            }
+            if proxy.no_proxy.iter().any(|host| host.trim().is_empty()) {
+                problems.push((
+                    at("no_proxy").value,
+                    "`no_proxy` entries must not be empty".to_string(),
+                ));
+            }
+        }
+
+        if let Some(reader_api) = &self.reader_api {
+            let at = |key: &str| locate(doc, &[Segment::key("reader_api"), Segment::key(key)]);
+            if reader_api.username.trim().is_empty() {
+                problems.push((at("username").value, "`username` must not be empty".to_string()));
+            }
+            if reader_api.password.is_empty() {
+                problems.push((at("password").value, "`password` must not be empty".to_string()));
+            }
+        }
+        problems
+    }
+}
+
+impl LinksConfig {
+    fn validate(&self, doc: &ImDocument<&str>) -> Vec<(Option<Range<usize>>, String)> {
+        let mut problems = Vec::new();
+
+        for (i, param) in self.strip_params.iter().enumerate() {
+            let name = param.strip_suffix('*').unwrap_or(param);
+            if name.is_empty() || name.contains(['*', '&', '=']) {
+                let path = [Segment::key("links"), Segment::key("strip_params"), Segment::Index(i)];
+                problems.push((
+                    locate(doc, &path).value,
+                    format!(
+                        "`{}` is not a parameter name; `*` is only allowed at the end",
+                        param
+                    ),
+                ));
+            }
+        }
+
+        for (i, rewrite) in self.rewrites.iter().enumerate() {
+            let at = |key: &str| {
+                let path = [
+                    Segment::key("links"),
+                    Segment::key("rewrites"),
+                    Segment::Index(i),
+                    Segment::key(key),
+                ];
+                locate(doc, &path)
+            };
+            if !is_host_name(&rewrite.domain) {
+                problems.push((
+                    at("domain").value,
+                    format!(
+                        "link rewrite domain `{}` must be a host name like `example.com`",
+                        rewrite.domain
+                    ),
+                ));
+            }
+            match &rewrite.host {
+                Some(host) if !is_host_name(host) => problems.push((
+                    at("host").value,
+                    format!("link rewrite host `{}` must be a host name", host),
+                )),
+                None if !rewrite.https => problems.push((
+                    at("domain").key,
+                    format!(
+                        "link rewrite for `{}` needs `https = true` or a `host`",
+                        rewrite.domain
+                    ),
+                )),
+                _ => {}
+            }
+        }
+        problems
+    }
+}
+
+/// A bare host name, optionally with a port: no scheme, path or credentials
+fn is_host_name(host: &str) -> bool {
+    !host.is_empty()
+        && !host.contains(['/', '@', '?', '#'])
+        && reqwest::Url::parse(&format!("https://{}/", host))
+            .is_ok_and(|url| url.host_str().is_some())
+}
+
+/// Check that `url` can be used as a proxy, describing the problem if not
+fn check_proxy_url(url: &str) -> Result<(), String> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if PROXY_SCHEMES.contains(&parsed.scheme()) && parsed.has_host() => Ok(()),
+        Ok(_) => Err(format!(
+            "`{}` must be an http, https, socks5 or socks5h URL with a host",
+            url
+        )),
+        Err(e) => Err(format!("`{}` is not a valid URL: {}", url, e)),
+    }
+}
+
+impl SiteConfig {
+    /// The branding with root-relative URLs, like `/static/logo.svg`, moved under `base`
+    pub fn under(mut self, base: &str) -> Self {
+        let move_url = |url: &mut String| {
+            if url.starts_with('/') && !url.starts_with("//") {
+                url.insert_str(0, base);
+            }
+        };
+        self.logo_url.iter_mut().for_each(move_url);
+        self.favicon.iter_mut().for_each(move_url);
+        self.custom_css.iter_mut().for_each(move_url);
+        self.custom_js.iter_mut().for_each(move_url);
+        self.footer_links.iter_mut().for_each(|link| move_url(&mut link.url));
+        self
+    }
+
+    fn validate(&self, doc: &ImDocument<&str>) -> Vec<(Option<Range<usize>>, String)> {
+        let at = |key: &str| locate(doc, &[Segment::key("site"), Segment::key(key)]).value;
+        let mut problems = Vec::new();
+
+        if self.title.trim().is_empty() {
+            problems.push((at("title"), "site title must not be empty".to_string()));
+        }
+        if let Some(svg) = &self.logo_svg {
+            if self.logo_url.is_some() {
+                problems.push((
+                    at("logo_url"),
+                    "set only one of `logo_svg` and `logo_url`".to_string(),
+                ));
+            }
+            if !svg.trim_start().starts_with("<svg") {
+                problems.push((at("logo_svg"), "`logo_svg` must be `<svg>` markup".to_string()));
+            }
+        }
+        for (i, link) in self.footer_links.iter().enumerate() {
+            if link.label.trim().is_empty() || link.url.trim().is_empty() {
+                let path = [Segment::key("site"), Segment::key("footer_links"), Segment::Index(i)];
+                problems.push((
+                    locate(doc, &path).value,
+                    "footer links need a `label` and a `url`".to_string(),
+                ));
+            }
+        }
+        problems
+    }
+}
+
+/// `#rgb` or `#rrggbb`, the only accent forms accepted so they can go straight into CSS
+fn is_hex_color(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|hex| {
+        (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Check a rule at `path` that needs exactly one of `keyword` or `regex`.
+/// A missing or doubled matcher is reported at `anchor`, or at the list if the
+/// rule has no such key.
+fn check_matcher(
+    doc: &ImDocument<&str>,
+    path: &[Segment],
+    anchor: &str,
+    keyword: Option<&str>,
+    regex: Option<&str>,
+    rule: &str,
+) -> Option<(Option<Range<usize>>, String)> {
+    let at = |key: &str| {
+        let mut path = path.to_vec();
+        path.push(Segment::key(key));
+        locate(doc, &path)
+    };
+
+    match (keyword, regex) {
+        (Some(_), Some(_)) | (None, None) => Some((
+            at(anchor).key.or_else(|| locate(doc, path).key),
+            format!("{} needs exactly one of `keyword` or `regex`", rule),
+        )),
+        (Some(keyword), None) if keyword.trim().is_empty() => Some((
+            at("keyword").value,
+            format!("{} has an empty keyword", rule),
+        )),
+        (None, Some(pattern)) => regex::Regex::new(pattern).err().map(|e| {
+            (
+                at("regex").value,
+                format!(
+                    "{} has an invalid regex: {}",
+                    rule,
+                    e.to_string().lines().last().unwrap_or_default()
+                ),
+            )
+        }),
+        _ => None,
+    }
+}
+
+/// Check that a feed's headers, credentials, user agent, timeout and proxy can be used
+fn check_http_options(
+    doc: &ImDocument<&str>,
+    index: usize,
+    feed: &FeedConfig,
+) -> Vec<(Option<Range<usize>>, String)> {
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    let at = |path: &[&str]| {
+        let mut segments = Segment::feed_field(index, path[0]);
+        segments.extend(path[1..].iter().map(|key| Segment::key(key)));
+        locate(doc, &segments)
+    };
+    let mut problems = Vec::new();
+
+    for (name, value) in &feed.headers {
+        let header_at = at(&["headers", name]);
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            problems.push((
+                header_at.key,
+                format!("feed `{}` has an invalid header name `{}`", feed.name, name),
+            ));
+        } else if HeaderValue::from_str(value).is_err() {
+            problems.push((
+                header_at.value,
+                format!("feed `{}` has an invalid value for header `{}`", feed.name, name),
+            ));
+        }
+    }
+
+    if let Some(auth) = &feed.auth {
+        match (&auth.bearer, &auth.username) {
+            (Some(_), Some(_)) | (None, None) => problems.push((
+                at(&["auth"]).key,
+                format!(
+                    "auth of feed `{}` needs exactly one of `bearer` or `username`",
+                    feed.name
+                ),
+            )),
+            (Some(_), None) if auth.password.is_some() => problems.push((
+                at(&["auth", "password"]).key,
+                format!(
+                    "auth of feed `{}` sets `password` without `username`",
+                    feed.name
+                ),
+            )),
+            (Some(token), None) if HeaderValue::from_str(&format!("Bearer {}", token)).is_err() => {
+                problems.push((
+                    at(&["auth", "bearer"]).value,
+                    format!("auth of feed `{}` has an invalid bearer token", feed.name),
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(agent) = &feed.user_agent {
+        if agent.trim().is_empty() || HeaderValue::from_str(agent).is_err() {
+            problems.push((
+                at(&["user_agent"]).value,
+                format!("feed `{}` has an invalid `user_agent`", feed.name),
+            ));
+        }
+    }
+
+    if feed.timeout_secs == Some(0) {
+        problems.push((
+            at(&["timeout_secs"]).value,
+            format!("feed `{}` has `timeout_secs` 0; it must be at least 1", feed.name),
+        ));
+    }
+
+    if let Some(proxy) = feed.proxy.as_deref().filter(|proxy| *proxy != DIRECT_PROXY) {
+        if let Err(message) = check_proxy_url(proxy) {
+            problems.push((
+                at(&["proxy"]).value,
+                format!("feed `{}` proxy {}; or use `{}`", feed.name, message, DIRECT_PROXY),
+            ));
+        }
+    }
+
+    problems
+}
+
+/// One problem found in a configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    /// File the problem is in, when it isn't the one named by `ConfigError::path`
+    pub file: Option<PathBuf>,
+    /// 1-based line of the offending key or value
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(content: &str, span: Option<Range<usize>>, message: String) -> Self {
+        let offset = span.map(|s| s.start).unwrap_or(0).min(content.len());
+        let before = &content[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Self {
+            file: None,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+        }
+    }
+
+    fn in_file(self, file: &Path) -> Self {
+        Self {
+            file: Some(file.to_path_buf()),
+            ..self
+        }
+    }
+}
+
+/// Every problem that stopped a configuration from loading
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: Option<PathBuf>,
+    pub problems: Vec<ConfigProblem>,
+}
+
+impl ConfigError {
+    fn single(content: &str, span: Option<Range<usize>>, message: &str) -> Self {
+        Self {
+            path: None,
+            problems: vec![ConfigProblem::new(content, span, message.trim().to_string())],
+        }
+    }
+
+    fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "config".to_string(),
+        };
+        let count = self.problems.len();
+        write!(
+            f,
+            "{} has {} problem{}:",
+            source,
+            count,
+            if count == 1 { "" } else { "s" }
+        )?;
+        for problem in &self.problems {
+            let file = match &problem.file {
+                Some(file) => file.display().to_string(),
+                None => source.clone(),
+            };
+            write!(
+                f,
+                "\n  {}:{}:{}: {}",
+                file, problem.line, problem.column, problem.message
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn read_config_file(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Files matching one `include` pattern, sorted by name. Only the file name may
+/// contain wildcards; a missing directory matches nothing.
+fn expand_include(base: &Path, pattern: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let full = base.join(pattern);
+    let dir = full.parent().unwrap_or(Path::new(""));
+    let Some(file_pattern) = full.file_name().and_then(|name| name.to_str()) else {
+        anyhow::bail!("Invalid include pattern `{}`", pattern);
+    };
+    if dir.to_string_lossy().contains(['*', '?']) {
+        anyhow::bail!(
+            "Invalid include pattern `{}`: wildcards are only supported in the file name",
+            pattern
+        );
+    }
+    if !file_pattern.contains(['*', '?']) {
+        return Ok(vec![full]);
+    }
+
+    let regex = file_pattern
+        .chars()
+        .map(|c| match c {
+            '*' => ".*".to_string(),
+            '?' => ".".to_string(),
+            c => regex::escape(&c.to_string()),
+        })
+        .collect::<String>();
+    let regex = regex::Regex::new(&format!("^{}$", regex))?;
+
+    let listing = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let entries = match std::fs::read_dir(listing) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to list {}", listing.display())),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        if regex.is_match(name) && !name.starts_with('.') && entry.file_type()?.is_file() {
+            files.push(dir.join(name));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Keys whose values are regex syntax, where `${name}` refers to a capture group
+const REGEX_KEYS: [&str; 2] = ["regex", "replace"];
+
+/// Replace `${NAME}` in every string value with the environment variable `NAME`.
+/// `$${` stands for a literal `${`. Values of `REGEX_KEYS` are left as written.
+fn interpolate_env(
+    value: &mut toml::Value,
+    path: &mut Vec<Segment>,
+    doc: &ImDocument<&str>,
+    problems: &mut Vec<(Option<Range<usize>>, String)>,
+) {
+    match value {
+        toml::Value::String(s) => match expand_env(s) {
+            Ok(expanded) => *s = expanded,
+            Err(message) => problems.push((locate(doc, path).value, message)),
+        },
+        toml::Value::Array(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                path.push(Segment::Index(i));
+                interpolate_env(value, path, doc, problems);
+                path.pop();
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if REGEX_KEYS.contains(&key.as_str()) {
+                    continue;
+                }
+                path.push(Segment::Key(key.clone()));
+                interpolate_env(value, path, doc, problems);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Expand `${NAME}` references in one string, the way values in the file are
+pub fn expand_env(s: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(after) = rest.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let Some(end) = after.find('}') else {
+                return Err("unterminated `${` (write `$${` for a literal `${`)".to_string());
+            };
+            let name = &after[..end];
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("invalid environment variable name `{}`", name));
+            }
+            let value = std::env::var(name)
+                .map_err(|_| format!("environment variable `{}` is not set", name))?;
+            out.push_str(&value);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A step in a path from the document root to a key
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl Segment {
+    fn key(key: &str) -> Self {
+        Segment::Key(key.to_string())
+    }
+
+    fn field(array: &str, index: usize, field: &str) -> Vec<Self> {
+        vec![Segment::key(array), Segment::Index(index), Segment::key(field)]
+    }
+
+    fn feed_field(index: usize, field: &str) -> Vec<Self> {
+        Self::field("feeds", index, field)
+    }
+}
+
+fn display_path(path: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if out.is_empty() => out.push_str(key),
+            Segment::Key(key) => {
+                out.push('.');
+                out.push_str(key);
+            }
+            Segment::Index(i) => out.push_str(&format!("[{}]", i)),
+        }
+    }
+    out
+}
+
+/// Source spans of a key and its value
+#[derive(Debug, Default)]
+struct Location {
+    key: Option<Range<usize>>,
+    value: Option<Range<usize>>,
+}
+
+/// Find where the key at `path` was written, following both `[table]`/`[[array]]`
+/// headers and inline tables and arrays
+fn locate(doc: &ImDocument<&str>, path: &[Segment]) -> Location {
+    let mut table: &dyn TableLike = doc.as_table();
+    let mut location = Location::default();
+    let mut segments = path.iter().peekable();
+
+    while let Some(segment) = segments.next() {
+        let Segment::Key(name) = segment else {
+            return Location::default();
+        };
+        let Some((key, item)) = table.get_key_value(name) else {
+            return Location::default();
+        };
+        location = Location {
+            key: key.span(),
+            value: item.span(),
+        };
+
+        let next = match segments.peek() {
+            None => break,
+            Some(Segment::Index(i)) => {
+                let i = *i;
+                segments.next();
+                item.as_array_of_tables()
+                    .and_then(|tables| tables.get(i))
+                    .map(|t| t as &dyn TableLike)
+                    .or_else(|| {
+                        item.as_array()
+                            .and_then(|array| array.get(i))
+                            .and_then(|value| value.as_inline_table())
+                            .map(|t| t as &dyn TableLike)
+                    })
+            }
+            Some(Segment::Key(_)) => item.as_table_like(),
+        };
+        match next {
+            Some(next) => table = next,
+            None if segments.peek().is_none() => break,
+            None => return Location::default(),
+        }
+    }
+
+    location
+}
+
+/// Keys present in the source that no config field consumed, as reported by serde
+/// while deserializing `raw` into `T`
+fn unknown_keys<T: DeserializeOwned>(
+    raw: &toml::Value,
+    doc: &ImDocument<&str>,
+) -> Vec<(Option<Range<usize>>, String)> {
+    let mut found = Vec::new();
+    let _: Result<T, _> = serde_ignored::deserialize(raw.clone(), |path| {
+        let mut segments = Vec::new();
+        collect_segments(&path, &mut segments);
+        found.push(segments);
+    });
+
+    found
+        .into_iter()
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            let (key, parent) = path.split_last().expect("unknown key paths are never empty");
+            let message = match (key, parent.is_empty()) {
+                (Segment::Key(key), true) => format!("unknown key `{}`", key),
+                (Segment::Key(key), false) => {
+                    format!("unknown key `{}` in `{}`", key, display_path(parent))
+                }
+                (Segment::Index(_), _) => format!("unexpected entry `{}`", display_path(&path)),
+            };
+            (locate(doc, &path).key, message)
+        })
+        .collect()
+}
+
+fn collect_segments(path: &serde_ignored::Path, segments: &mut Vec<Segment>) {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => {}
+        Path::Seq { parent, index } => {
+            collect_segments(parent, segments);
+            segments.push(Segment::Index(*index));
+        }
+        Path::Map { parent, key } => {
+            collect_segments(parent, segments);
+            segments.push(Segment::Key(key.clone()));
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => collect_segments(parent, segments),
+    }
 }
 
 #[cfg(test)]
@@ -138,6 +1502,34 @@ mod tests {
         assert!(config.feeds.is_empty());
     }
 
+    #[test]
+    fn test_database_config_defaults() {
+        let config = Config::from_str("feeds = []").unwrap();
+
+        assert_eq!(config.database.max_connections, 5);
+        assert_eq!(config.database.busy_timeout_ms, 5000);
+        assert_eq!(config.database.journal_mode, "wal");
+        assert_eq!(config.database.synchronous, "normal");
+    }
+
+    #[test]
+    fn test_database_config_overrides() {
+        let content = r#"
+            feeds = []
+
+            [database]
+            max_connections = 2
+            journal_mode = "delete"
+        "#;
+
+        let config = Config::from_str(content).unwrap();
+
+        assert_eq!(config.database.max_connections, 2);
+        assert_eq!(config.database.journal_mode, "delete");
+        assert_eq!(config.database.busy_timeout_ms, 5000); // Default
+        assert_eq!(config.database.synchronous, "normal"); // Default
+    }
+
     #[test]
     fn test_multiple_feeds_with_mixed_settings() {
         let content = r#"
@@ -167,4 +1559,705 @@ mod tests {
         assert!(!config.feeds[1].has_discussion);
         assert!(!config.feeds[2].has_discussion); // Default
     }
+
+    fn problems(content: &str) -> Vec<ConfigProblem> {
+        Config::parse(content).unwrap_err().problems
+    }
+
+    #[test]
+    fn test_zero_refresh_interval_rejected() {
+        let problems = problems("refresh_interval = 0\nfeeds = []");
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!((problems[0].line, problems[0].column), (1, 20));
+        assert!(problems[0].message.contains("refresh_interval"));
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        let content = r#"refresh_intervall = 5
+
+[database]
+max_conections = 2
+
+[[feeds]]
+name = "Test Feed"
+url = "https://example.com/feed.xml"
+has_discusion = true
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 3);
+        assert_eq!((problems[0].line, problems[0].column), (1, 1));
+        assert_eq!(problems[0].message, "unknown key `refresh_intervall`");
+        assert_eq!((problems[1].line, problems[1].column), (4, 1));
+        assert_eq!(problems[1].message, "unknown key `max_conections` in `database`");
+        assert_eq!((problems[2].line, problems[2].column), (9, 1));
+        assert_eq!(problems[2].message, "unknown key `has_discusion` in `feeds[0]`");
+    }
+
+    #[test]
+    fn test_skipped_fields_are_unknown_keys() {
+        let content = r#"disabled_feeds = []
+
+[[feeds]]
+name = "Test Feed"
+url = "https://example.com/feed.xml"
+source = "other.toml"
+"#;
+
+        let problems = problems(content);
+        let messages: Vec<&str> = problems.iter().map(|p| p.message.as_str()).collect();
+
+        assert_eq!(
+            messages,
+            vec!["unknown key `disabled_feeds`", "unknown key `source` in `feeds[0]`"]
+        );
+    }
+
+    #[test]
+    fn test_all_feed_problems_collected() {
+        let content = r#"
+[[feeds]]
+name = "News"
+url = "https://example.com/rss"
+
+[[feeds]]
+name = "News"
+url = "https://example.com/rss"
+
+[[feeds]]
+name = "  "
+url = "ftp://example.com/rss"
+
+[[feeds]]
+name = "Broken"
+url = "not a url"
+"#;
+
+        let problems = problems(content);
+        let messages: Vec<&str> = problems.iter().map(|p| p.message.as_str()).collect();
+
+        assert_eq!(problems.len(), 5);
+        assert_eq!(messages[0], "duplicate feed name `News` (also used by feeds[0])");
+        assert_eq!(
+            messages[1],
+            "duplicate feed URL `https://example.com/rss` (also used by feeds[0])"
+        );
+        assert_eq!(messages[2], "feed name must not be empty");
+        assert!(messages[3].contains("only http and https"));
+        assert!(messages[4].starts_with("feed `Broken` has an invalid URL"));
+        assert_eq!((problems[0].line, problems[0].column), (7, 8));
+        assert_eq!((problems[1].line, problems[1].column), (8, 7));
+    }
+
+    #[test]
+    fn test_inline_feeds_are_located() {
+        let content = r#"feeds = [
+    { name = "A", url = "https://a.com/rss", colour = "red" },
+]"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!((problems[0].line, problems[0].column), (2, 46));
+        assert_eq!(problems[0].message, "unknown key `colour` in `feeds[0]`");
+    }
+
+    #[test]
+    fn test_type_errors_are_located() {
+        let problems = problems("refresh_interval = \"soon\"\nfeeds = []");
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, 1);
+    }
+
+    #[test]
+    fn test_error_report_names_file() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(b"refresh_interval = 0\nfeeds = []\n")
+            .unwrap();
+
+        let err = Config::load(temp_file.path()).unwrap_err();
+        let report = err.to_string();
+
+        assert!(report.contains("has 1 problem:"));
+        assert!(report.contains(&format!("{}:1:20:", temp_file.path().display())));
+    }
+
+    #[test]
+    fn test_groups() {
+        let content = r#"
+            [[groups]]
+            name = "News"
+            order = 2
+
+            [[groups]]
+            name = "Tech & Science"
+            collapsed = true
+
+            [[feeds]]
+            name = "Ars"
+            url = "https://ars.example.com/rss"
+            group = "Tech & Science"
+        "#;
+
+        let config = Config::from_str(content).unwrap();
+        let groups = config.sorted_groups();
+
+        assert_eq!(groups[0].name, "Tech & Science");
+        assert!(groups[0].collapsed);
+        assert_eq!(groups[0].slug(), "tech-science");
+        assert_eq!(groups[1].name, "News");
+        assert!(!groups[1].collapsed);
+    }
+
+    #[test]
+    fn test_group_slugs_must_be_unique() {
+        let content = r#"
+feeds = []
+
+[[groups]]
+name = "Tech"
+
+[[groups]]
+name = "tech!"
+
+[[groups]]
+name = "***"
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].line, 8);
+        assert!(problems[0].message.contains("same URL slug `tech`"));
+        assert_eq!(problems[1].line, 11);
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Tech & Science"), "tech-science");
+        assert_eq!(slugify("  Hello, World!  "), "hello-world");
+        assert_eq!(slugify("Nachrichten Übersicht"), "nachrichten-übersicht");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn test_filter_rules() {
+        let content = r#"
+            [[feeds]]
+            name = "Daring Fireball"
+            url = "https://daringfireball.net/feeds/main"
+            exclude = [
+                { field = "title", keyword = "[Sponsor]" },
+                { field = "category", regex = "(?i)^deals?$" },
+            ]
+            include = [{ field = "domain", keyword = "daringfireball.net" }]
+        "#;
+
+        let config = Config::from_str(content).unwrap();
+        let feed = &config.feeds[0];
+
+        assert_eq!(feed.exclude.len(), 2);
+        assert_eq!(feed.exclude[0].field, FilterField::Title);
+        assert_eq!(feed.exclude[0].keyword.as_deref(), Some("[Sponsor]"));
+        assert_eq!(feed.exclude[1].field, FilterField::Category);
+        assert_eq!(feed.include[0].field, FilterField::Domain);
+    }
+
+    #[test]
+    fn test_invalid_filter_rules() {
+        let content = r#"
+[[feeds]]
+name = "Feed"
+url = "https://example.com/rss"
+
+[[feeds.exclude]]
+field = "title"
+regex = "(unclosed"
+
+[[feeds.exclude]]
+field = "author"
+keyword = "a"
+regex = "b"
+
+[[feeds.include]]
+field = "title"
+keyword = " "
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 3);
+        assert_eq!((problems[0].line, problems[0].column), (8, 9));
+        assert!(problems[0].message.contains("invalid regex"));
+        assert_eq!(problems[1].line, 11);
+        assert!(problems[1].message.contains("exactly one of"));
+        assert_eq!(problems[2].line, 17);
+        assert!(problems[2].message.contains("empty keyword"));
+    }
+
+    #[test]
+    fn test_unknown_filter_field() {
+        let content = r#"
+            [[feeds]]
+            name = "Feed"
+            url = "https://example.com/rss"
+            exclude = [{ field = "body", keyword = "x" }]
+        "#;
+
+        assert!(Config::from_str(content).is_err());
+    }
+
+    #[test]
+    fn test_keyword_lists() {
+        let content = r#"
+feeds = []
+
+[keywords]
+highlight = [{ keyword = "Rust" }, { regex = "(?i)moar" }]
+mute = [{ regex = "[" }, {}]
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 2);
+        assert_eq!((problems[0].line, problems[0].column), (6, 1));
+        assert_eq!(problems[0].message, "mute rule needs exactly one of `keyword` or `regex`");
+        assert_eq!((problems[1].line, problems[1].column), (6, 19));
+        assert!(problems[1].message.starts_with("mute rule has an invalid regex"));
+
+        let config = Config::from_str(&content.replace("mute = [{ regex = \"[\" }, {}]", ""))
+            .unwrap();
+        assert_eq!(config.keywords.highlight.len(), 2);
+        assert!(config.keywords.mute.is_empty());
+    }
+
+    #[test]
+    fn test_site_branding() {
+        let config = Config::from_str("feeds = []").unwrap();
+        assert_eq!(config.site.title, "Moar News");
+        assert_eq!(config.site.footer_links[0].label, "View Source");
+
+        let config = Config::from_str(
+            r#"
+feeds = []
+
+[site]
+title = "Team News"
+tagline = "What we read"
+logo_url = "/static/logo.png"
+footer_links = [{ label = "Wiki", url = "https://wiki.example.com" }]
+custom_css = ["/static/team.css"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.site.title, "Team News");
+        assert_eq!(config.site.tagline.as_deref(), Some("What we read"));
+        assert_eq!(config.site.footer_links.len(), 1);
+        assert_eq!(config.site.custom_css, vec!["/static/team.css"]);
+
+        let problems = problems(
+            r#"
+feeds = []
+
+[site]
+title = " "
+logo_svg = "logo.svg"
+logo_url = "/static/logo.png"
+footer_links = [{ label = "Wiki", url = "" }]
+"#,
+        );
+        let messages: Vec<&str> = problems.iter().map(|p| p.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "site title must not be empty",
+                "`logo_svg` must be `<svg>` markup",
+                "set only one of `logo_svg` and `logo_url`",
+                "footer links need a `label` and a `url`",
+            ]
+        );
+        assert_eq!(problems[3].line, 8);
+    }
+
+    #[test]
+    fn test_feed_display_options() {
+        let content = r#"
+[[feeds]]
+name = "Styled"
+url = "https://example.com/rss"
+items_per_page = 0
+max_age_days = 0
+accent = "red"
+width = "wide"
+collapsed = true
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].message.contains("`items_per_page` 0"));
+        assert!(problems[1].message.contains("`max_age_days` 0"));
+        assert_eq!((problems[2].line, problems[2].column), (7, 10));
+        assert!(problems[2].message.contains("hex color"));
+
+        let content = content
+            .replace("items_per_page = 0", "items_per_page = 30")
+            .replace("max_age_days = 0", "max_age_days = 7")
+            .replace("\"red\"", "\"#E85A1B\"");
+        let feed = &Config::from_str(&content).unwrap().feeds[0];
+        assert_eq!(feed.items_per_page, Some(30));
+        assert_eq!(feed.width, ColumnWidth::Wide);
+        assert!(feed.collapsed);
+    }
+
+    #[test]
+    fn test_feed_http_options() {
+        let content = r#"
+[[feeds]]
+name = "Private"
+url = "https://example.com/rss"
+headers = { "Bad Name" = "x", "X-Api-Key" = "abc" }
+auth = { bearer = "token", username = "me" }
+user_agent = ""
+timeout_secs = 0
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 4);
+        assert_eq!((problems[0].line, problems[0].column), (5, 13));
+        assert!(problems[0].message.contains("invalid header name `Bad Name`"));
+        assert!(problems[1].message.contains("exactly one of `bearer` or `username`"));
+        assert!(problems[2].message.contains("`user_agent`"));
+        assert!(problems[3].message.contains("`timeout_secs` 0"));
+
+        let content = content
+            .replace("\"Bad Name\" = \"x\", ", "")
+            .replace("bearer = \"token\", ", "")
+            .replace("username = \"me\"", "username = \"me\", password = \"pw\"")
+            .replace("user_agent = \"\"", "user_agent = \"Mozilla/5.0\"")
+            .replace("timeout_secs = 0", "timeout_secs = 90");
+        let feed = &Config::from_str(&content).unwrap().feeds[0];
+        assert_eq!(feed.headers["X-Api-Key"], "abc");
+        let auth = feed.auth.as_ref().unwrap();
+        assert_eq!(auth.username.as_deref(), Some("me"));
+        assert_eq!(auth.password.as_deref(), Some("pw"));
+        assert_eq!(feed.user_agent.as_deref(), Some("Mozilla/5.0"));
+        assert_eq!(feed.timeout_secs, Some(90));
+    }
+
+    #[test]
+    fn test_proxy_settings() {
+        let content = r#"
+[proxy]
+url = "ftp://proxy.corp:21"
+no_proxy = ["intranet.corp", ""]
+
+[[feeds]]
+name = "Internal"
+url = "https://intranet.corp/rss"
+proxy = "nearby"
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 3);
+        assert_eq!((problems[0].line, problems[0].column), (3, 7));
+        assert!(problems[0].message.contains("http, https, socks5 or socks5h"));
+        assert!(problems[1].message.contains("`no_proxy` entries"));
+        assert_eq!(problems[2].line, 9);
+        assert!(problems[2].message.contains("feed `Internal` proxy `nearby` is not a valid URL"));
+
+        let content = content
+            .replace("ftp://proxy.corp:21", "socks5h://user:pw@proxy.corp:1080")
+            .replace(", \"\"", "")
+            .replace("\"nearby\"", "\"direct\"");
+        let config = Config::from_str(&content).unwrap();
+        let proxy = config.proxy.unwrap();
+        assert_eq!(proxy.url, "socks5h://user:pw@proxy.corp:1080");
+        assert_eq!(proxy.no_proxy, vec!["intranet.corp"]);
+        assert_eq!(config.feeds[0].proxy.as_deref(), Some(DIRECT_PROXY));
+    }
+
+    #[test]
+    fn test_reader_api_credentials() {
+        let content = r#"
+feeds = []
+
+[reader_api]
+username = " "
+password = ""
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 2);
+        assert_eq!((problems[0].line, problems[0].column), (5, 12));
+        assert!(problems[0].message.contains("`username` must not be empty"));
+        assert!(problems[1].message.contains("`password` must not be empty"));
+
+        let content = content
+            .replace("\" \"", "\"team\"")
+            .replace("password = \"\"", "password = \"hunter2\"");
+        let reader_api = Config::from_str(&content).unwrap().reader_api.unwrap();
+        assert_eq!(reader_api.username, "team");
+        assert_eq!(reader_api.password, "hunter2");
+        assert!(Config::from_str("feeds = []").unwrap().reader_api.is_none());
+    }
+
+    #[test]
+    fn test_title_rewrites() {
+        let content = r#"
+[[feeds]]
+name = "Noisy"
+url = "https://example.com/rss"
+title_rewrites = [{ regex = "^★\\s*" }, { regex = "(unclosed", replace = "x" }]
+collapse_whitespace = true
+strip_site_suffix = true
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, 5);
+        assert!(problems[0].message.contains("title rewrite of feed `Noisy` has an invalid regex"));
+
+        let content = content.replace("(unclosed", r"(?i)^\\[video\\]");
+        let feed = &Config::from_str(&content).unwrap().feeds[0];
+        assert_eq!(feed.title_rewrites.len(), 2);
+        assert_eq!(feed.title_rewrites[0].regex, "^★\\s*");
+        assert_eq!(feed.title_rewrites[0].replace, "");
+        assert!(feed.collapse_whitespace && feed.strip_site_suffix);
+    }
+
+    #[test]
+    fn test_link_settings() {
+        let content = r#"
+feeds = []
+
+[links]
+strip_params = ["utm_*", "*", "fbclid"]
+
+[[links.rewrites]]
+domain = "https://twitter.com"
+host = "nitter.example"
+
+[[links.rewrites]]
+domain = "example.com"
+"#;
+
+        let problems = problems(content);
+
+        assert_eq!(problems.len(), 3);
+        assert_eq!((problems[0].line, problems[0].column), (5, 16));
+        assert!(problems[0].message.contains("`*` is not a parameter name"));
+        assert!(problems[1].message.contains("must be a host name like `example.com`"));
+        assert_eq!(problems[2].line, 12);
+        assert!(problems[2].message.contains("needs `https = true` or a `host`"));
+
+        let content = content
+            .replace("\"*\", ", "")
+            .replace("https://twitter.com", "twitter.com")
+            .replace("domain = \"example.com\"", "domain = \"example.com\"\nhttps = true");
+        let links = Config::from_str(&content).unwrap().links;
+        assert_eq!(links.strip_params, vec!["utm_*", "fbclid"]);
+        assert_eq!(links.rewrites[0].host.as_deref(), Some("nitter.example"));
+        assert!(links.rewrites[1].https);
+    }
+
+    #[test]
+    fn test_disabled_feeds_are_set_aside() {
+        let config = Config::from_str(
+            r#"
+[[feeds]]
+name = "On"
+url = "https://on.com/rss"
+
+[[feeds]]
+name = "Off"
+url = "https://off.com/rss"
+disabled = true
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.feeds.len(), 1);
+        assert_eq!(config.feeds[0].name, "On");
+        assert_eq!(config.disabled_feeds.len(), 1);
+        assert_eq!(config.disabled_feeds[0].name, "Off");
+
+        // A disabled feed still claims its URL
+        let problems = problems(
+            r#"
+[[feeds]]
+name = "On"
+url = "https://on.com/rss"
+
+[[feeds]]
+name = "Off"
+url = "https://on.com/rss"
+disabled = true
+"#,
+        );
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("duplicate feed URL"));
+    }
+
+    mod include_tests {
+        use super::*;
+
+        fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, content).unwrap();
+            path
+        }
+
+        fn feed(name: &str) -> String {
+            format!(
+                "[[feeds]]\nname = \"{}\"\nurl = \"https://{}.example.com/rss\"\n",
+                name,
+                name.to_lowercase()
+            )
+        }
+
+        #[test]
+        fn test_include_merges_in_order() {
+            let dir = tempfile::tempdir().unwrap();
+            let main = write(
+                dir.path(),
+                "feeds.toml",
+                &format!("include = [\"feeds.d/*.toml\", \"extra.toml\"]\n{}", feed("Main")),
+            );
+            write(dir.path(), "feeds.d/b.toml", &feed("Bravo"));
+            write(
+                dir.path(),
+                "feeds.d/a.toml",
+                &format!("[[groups]]\nname = \"Shared\"\n{}", feed("Alpha")),
+            );
+            write(dir.path(), "feeds.d/notes.txt", "not toml");
+            write(dir.path(), "extra.toml", &feed("Extra"));
+
+            let config = Config::load(&main).unwrap();
+
+            let names: Vec<&str> = config.feeds.iter().map(|f| f.name.as_str()).collect();
+            assert_eq!(names, vec!["Main", "Alpha", "Bravo", "Extra"]);
+            assert_eq!(config.groups[0].name, "Shared");
+            assert_eq!(config.feeds[0].source.as_deref(), Some(main.as_path()));
+            assert_eq!(
+                config.feeds[1].source.as_deref(),
+                Some(dir.path().join("feeds.d/a.toml").as_path())
+            );
+        }
+
+        #[test]
+        fn test_include_missing_directory_matches_nothing() {
+            let dir = tempfile::tempdir().unwrap();
+            let main = write(
+                dir.path(),
+                "feeds.toml",
+                &format!("include = [\"personal.d/*.toml\"]\n{}", feed("Main")),
+            );
+
+            assert_eq!(Config::load(&main).unwrap().feeds.len(), 1);
+
+            write(dir.path(), "feeds.toml", "include = [\"missing.toml\"]\nfeeds = []");
+            let error = Config::load(&main).unwrap_err().to_string();
+            assert!(error.contains("Failed to read"));
+            assert!(error.contains("missing.toml"));
+        }
+
+        #[test]
+        fn test_include_problems_name_their_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let main = write(
+                dir.path(),
+                "feeds.toml",
+                &format!("include = [\"feeds.d/*.toml\"]\n{}", feed("Main")),
+            );
+            write(
+                dir.path(),
+                "feeds.d/a.toml",
+                "[[feeds]]\nname = \"Copy\"\nurl = \"https://main.example.com/rss\"\n",
+            );
+            write(dir.path(), "feeds.d/b.toml", "refresh_interval = 5\nfeeds = []\n");
+
+            let error = Config::load(&main).unwrap_err();
+            let error = error.downcast_ref::<ConfigError>().unwrap();
+
+            assert_eq!(error.problems.len(), 2);
+            let a = dir.path().join("feeds.d/a.toml");
+            assert_eq!(error.problems[0].file.as_deref(), Some(a.as_path()));
+            assert_eq!((error.problems[0].line, error.problems[0].column), (3, 7));
+            assert!(error.problems[0].message.contains("also used in"));
+            assert!(error.problems[1].message.contains("unknown key `refresh_interval`"));
+            assert!(error
+                .to_string()
+                .contains(&format!("{}:3:7: duplicate feed URL", a.display())));
+        }
+
+        #[test]
+        fn test_env_interpolation() {
+            std::env::set_var("MOAR_NEWS_TEST_HOST", "private.example.com");
+            let config = Config::from_str(
+                r#"
+                [[feeds]]
+                name = "Private ${MOAR_NEWS_TEST_HOST}"
+                url = "https://${MOAR_NEWS_TEST_HOST}/rss?cost=$5"
+                group = "Literal $${NOT_A_VAR}"
+                "#,
+            )
+            .unwrap();
+
+            assert_eq!(config.feeds[0].name, "Private private.example.com");
+            assert_eq!(config.feeds[0].url, "https://private.example.com/rss?cost=$5");
+            assert_eq!(
+                config.feeds[0].url_template.as_deref(),
+                Some("https://${MOAR_NEWS_TEST_HOST}/rss?cost=$5")
+            );
+            assert_eq!(config.feeds[0].group.as_deref(), Some("Literal ${NOT_A_VAR}"));
+        }
+
+        #[test]
+        fn test_regex_values_not_interpolated() {
+            let config = Config::from_str(
+                r#"
+                [[feeds]]
+                name = "Feed"
+                url = "https://example.com/rss"
+                title_rewrites = [{ regex = "^(?<show>\\w+):", replace = "${show} -" }]
+                "#,
+            )
+            .unwrap();
+
+            let rewrite = &config.feeds[0].title_rewrites[0];
+            assert_eq!(rewrite.regex, r"^(?<show>\w+):");
+            assert_eq!(rewrite.replace, "${show} -");
+        }
+
+        #[test]
+        fn test_env_interpolation_problems() {
+            let content = r#"
+[[feeds]]
+name = "Feed"
+url = "https://${MOAR_NEWS_TEST_UNSET_VAR}/rss"
+group = "${oops"
+"#;
+
+            let problems = problems(content);
+
+            assert_eq!(problems.len(), 2);
+            assert_eq!((problems[0].line, problems[0].column), (4, 7));
+            assert_eq!(
+                problems[0].message,
+                "environment variable `MOAR_NEWS_TEST_UNSET_VAR` is not set"
+            );
+            assert!(problems[1].message.starts_with("unterminated"));
+        }
+    }
 }
@@ -1,9 +1,22 @@
-use chrono::{DateTime, Utc};
-use sqlx::{sqlite::SqlitePoolOptions, FromRow, SqlitePool};
-
-use crate::config::FeedConfig;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
 
-#[derive(Debug, Clone, FromRow)]
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    query::QueryAs,
+    sqlite::{
+        SqliteArguments, SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions,
+        SqliteSynchronous,
+    },
+    FromRow, Sqlite, SqlitePool,
+};
+
+use crate::config::{DatabaseConfig, FeedConfig};
+use crate::opml::OpmlFeed;
+
+#[derive(Debug, Clone, Default, FromRow, Serialize)]
 pub struct Feed {
     pub id: i64,
     pub name: String,
@@ -12,9 +25,41 @@ pub struct Feed {
     pub last_fetched: Option<String>,
     pub last_error: Option<String>,
     pub homepage_url: Option<String>,
+    pub group_name: Option<String>,
+    /// The URL as written in the config, when it refers to environment variables
+    #[serde(skip)]
+    pub url_template: Option<String>,
 }
 
-#[derive(Debug, Clone, FromRow)]
+impl Feed {
+    /// The URL to show outside the admin pages: environment variables are left
+    /// as `${NAME}` and credentials in the authority are dropped
+    pub fn public_url(&self) -> String {
+        without_userinfo(self.url_template.as_deref().unwrap_or(&self.url))
+    }
+
+    /// The feed with `url` replaced by `public_url`, for public output
+    pub fn with_public_url(self) -> Self {
+        Feed {
+            url: self.public_url(),
+            ..self
+        }
+    }
+}
+
+/// `url` without a `user:password@` part
+fn without_userinfo(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    match rest[..authority_end].rfind('@') {
+        Some(at) => format!("{}://{}", scheme, &rest[at + 1..]),
+        None => url.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
 pub struct Item {
     pub id: i64,
     pub feed_id: i64,
@@ -23,6 +68,235 @@ pub struct Item {
     pub link: String,
     pub discussion_link: Option<String>,
     pub published: Option<String>,
+    /// Number of recorded edits to title, link or published date
+    pub revision_count: i64,
+    /// Link as the feed gave it, when `[links]` settings changed it
+    pub original_link: Option<String>,
+    /// Marked read in a reader app
+    pub is_read: bool,
+    /// Saved (starred) in a reader app
+    pub is_saved: bool,
+    /// When the item was first stored
+    pub inserted_at: Option<String>,
+}
+
+/// Previous values of an item, recorded when a refresh changed them
+#[derive(Debug, Clone, FromRow)]
+pub struct ItemRevision {
+    pub id: i64,
+    pub item_id: i64,
+    pub title: String,
+    pub link: String,
+    pub published: Option<String>,
+    pub revised_at: String,
+}
+
+impl ItemRevision {
+    /// `revised_at` formatted for display, e.g. "2024-12-09 12:00 UTC"
+    pub fn revised_at_display(&self) -> String {
+        DateTime::parse_from_rfc3339(&self.revised_at)
+            .map(|dt| dt.with_timezone(&Utc).format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|_| self.revised_at.clone())
+    }
+}
+
+/// One fetch attempt for a feed, as stored in `fetch_log`
+#[derive(Debug, Clone, FromRow)]
+pub struct FetchRecord {
+    pub feed_id: i64,
+    pub started_at: String,
+    pub duration_ms: i64,
+    pub http_status: Option<i64>,
+    pub bytes: Option<i64>,
+    pub new_items: i64,
+    pub updated_items: i64,
+    /// Entries dropped by the feed's include/exclude rules
+    pub filtered_items: i64,
+    /// Coarse failure class ("network", "http", "parse", "database"); `None` on success
+    pub error_category: Option<String>,
+}
+
+/// How long fetch log entries are kept
+pub const FETCH_LOG_RETENTION_DAYS: i64 = 30;
+
+/// What `upsert_item` did with an incoming item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    /// The item was new
+    Inserted,
+    /// The item existed and its title, link or published date changed
+    Updated,
+    /// The item existed with the same title, link and published date
+    Unchanged,
+}
+
+/// Which items `find_items` returns, in id order. Unset fields don't filter.
+#[derive(Debug, Clone, Default)]
+pub struct ItemFilter {
+    /// Only items of these feeds
+    pub feed_ids: Option<Vec<i64>>,
+    pub is_read: Option<bool>,
+    pub is_saved: Option<bool>,
+    /// Only items published at or after this time, and undated ones
+    pub published_since: Option<DateTime<Utc>>,
+    /// Only items published before this time
+    pub published_before: Option<DateTime<Utc>>,
+    /// Resume after the item with this id, in the listing's direction
+    pub after_id: Option<i64>,
+    /// Lowest id first instead of highest
+    pub oldest_first: bool,
+    pub limit: i64,
+}
+
+/// Position of an item in a feed's listing, used for keyset pagination.
+///
+/// Items are ordered by `published DESC NULLS LAST, id DESC`, so the sort key
+/// of the last item seen plus its id is enough to resume the listing even if
+/// new items are inserted in between page loads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemCursor {
+    pub published: Option<String>,
+    pub id: i64,
+}
+
+impl ItemCursor {
+    pub fn from_item(item: &Item) -> Self {
+        Self {
+            published: item.published.clone(),
+            id: item.id,
+        }
+    }
+}
+
+/// Encodes as `<id>` or `<id>:<published>` for use in query strings
+impl std::fmt::Display for ItemCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.published {
+            Some(published) => write!(f, "{}:{}", self.id, published),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+impl std::str::FromStr for ItemCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, published) = match s.split_once(':') {
+            Some((id, published)) => (id, Some(published.to_string())),
+            None => (s, None),
+        };
+        let id = id
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid cursor: {}", s))?;
+        Ok(Self { published, id })
+    }
+}
+
+/// Limits a cross-feed item query to the items of feeds that aren't disabled
+const ENABLED_ITEMS: &str = "feed_id IN (SELECT id FROM feeds WHERE disabled = 0)";
+
+type ItemQuery<'q> = QueryAs<'q, Sqlite, Item, SqliteArguments<'q>>;
+
+/// SQL condition keeping the items that sort strictly after `after`.
+///
+/// Undated items sort last, so a dated cursor is followed by older dated items
+/// and then every undated item; an undated cursor only by undated ones.
+fn cursor_condition(after: Option<&ItemCursor>) -> &'static str {
+    match after {
+        None => "",
+        Some(ItemCursor {
+            published: Some(_), ..
+        }) => "AND (published < ? OR (published = ? AND id < ?) OR published IS NULL)",
+        Some(ItemCursor { published: None, .. }) => "AND published IS NULL AND id < ?",
+    }
+}
+
+/// Bind the parameters of `cursor_condition(after)`
+fn bind_cursor<'q>(mut query: ItemQuery<'q>, after: Option<&'q ItemCursor>) -> ItemQuery<'q> {
+    if let Some(cursor) = after {
+        if let Some(published) = &cursor.published {
+            query = query.bind(published).bind(published);
+        }
+        query = query.bind(cursor.id);
+    }
+    query
+}
+
+/// Pragma values as reported by SQLite on a live connection
+#[derive(Debug, Clone)]
+pub struct EffectivePragmas {
+    pub journal_mode: String,
+    pub synchronous: i64,
+    pub busy_timeout_ms: i64,
+    pub foreign_keys: bool,
+    pub max_connections: u32,
+}
+
+impl std::fmt::Display for EffectivePragmas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let synchronous = match self.synchronous {
+            0 => "off",
+            1 => "normal",
+            2 => "full",
+            3 => "extra",
+            _ => "unknown",
+        };
+        write!(
+            f,
+            "journal_mode={} synchronous={} busy_timeout={}ms foreign_keys={} max_connections={}",
+            self.journal_mode,
+            synchronous,
+            self.busy_timeout_ms,
+            if self.foreign_keys { "on" } else { "off" },
+            self.max_connections
+        )
+    }
+}
+
+/// A feed and its items as written to export documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedSnapshot {
+    pub name: String,
+    pub url: String,
+    pub has_discussion: bool,
+    pub homepage_url: Option<String>,
+    #[serde(default)]
+    pub group_name: Option<String>,
+    pub last_fetched: Option<String>,
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    pub items: Vec<ItemSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemSnapshot {
+    pub guid: String,
+    pub title: String,
+    pub link: String,
+    /// Link as the feed gave it, when it was cleaned up before storing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_link: Option<String>,
+    pub discussion_link: Option<String>,
+    pub published: Option<String>,
+    /// Marked read in a reader app
+    #[serde(default)]
+    pub is_read: bool,
+    /// Saved (starred) in a reader app
+    #[serde(default)]
+    pub is_saved: bool,
+    /// Previous versions, most recent edit first
+    #[serde(default)]
+    pub revisions: Vec<RevisionSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevisionSnapshot {
+    pub title: String,
+    pub link: String,
+    pub published: Option<String>,
+    pub revised_at: String,
 }
 
 pub struct Database {
@@ -31,14 +305,55 @@ pub struct Database {
 
 impl Database {
     pub async fn new(database_url: &str) -> anyhow::Result<Self> {
+        Self::connect(database_url, &DatabaseConfig::default()).await
+    }
+
+    /// Open a connection pool with the journal, sync and timeout settings from `config`
+    pub async fn connect(database_url: &str, config: &DatabaseConfig) -> anyhow::Result<Self> {
+        let journal_mode = SqliteJournalMode::from_str(&config.journal_mode)
+            .map_err(|_| anyhow::anyhow!("Invalid journal_mode: {}", config.journal_mode))?;
+        let synchronous = SqliteSynchronous::from_str(&config.synchronous)
+            .map_err(|_| anyhow::anyhow!("Invalid synchronous: {}", config.synchronous))?;
+
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .journal_mode(journal_mode)
+            .synchronous(synchronous)
+            .busy_timeout(Duration::from_millis(config.busy_timeout_ms))
+            .foreign_keys(true);
+
         let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect(database_url)
+            .max_connections(config.max_connections)
+            .connect_with(options)
             .await?;
 
         Ok(Self { pool })
     }
 
+    /// Read back the pragmas actually in force, for logging at startup
+    pub async fn effective_pragmas(&self) -> anyhow::Result<EffectivePragmas> {
+        let mut conn = self.pool.acquire().await?;
+        let (journal_mode,): (String,) = sqlx::query_as("PRAGMA journal_mode")
+            .fetch_one(&mut *conn)
+            .await?;
+        let (synchronous,): (i64,) = sqlx::query_as("PRAGMA synchronous")
+            .fetch_one(&mut *conn)
+            .await?;
+        let (busy_timeout_ms,): (i64,) = sqlx::query_as("PRAGMA busy_timeout")
+            .fetch_one(&mut *conn)
+            .await?;
+        let (foreign_keys,): (bool,) = sqlx::query_as("PRAGMA foreign_keys")
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok(EffectivePragmas {
+            journal_mode,
+            synchronous,
+            busy_timeout_ms,
+            foreign_keys,
+            max_connections: self.pool.options().get_max_connections(),
+        })
+    }
+
     pub async fn initialize(&self) -> anyhow::Result<()> {
         sqlx::query(
             r#"
@@ -61,6 +376,26 @@ impl Database {
             .execute(&self.pool)
             .await;
 
+        // Migration: add group_name column if it doesn't exist
+        let _ = sqlx::query("ALTER TABLE feeds ADD COLUMN group_name TEXT")
+            .execute(&self.pool)
+            .await;
+
+        // Migration: add position column if it doesn't exist
+        let _ = sqlx::query("ALTER TABLE feeds ADD COLUMN position INTEGER")
+            .execute(&self.pool)
+            .await;
+
+        // Migration: add disabled column if it doesn't exist
+        let _ = sqlx::query("ALTER TABLE feeds ADD COLUMN disabled INTEGER NOT NULL DEFAULT 0")
+            .execute(&self.pool)
+            .await;
+
+        // Migration: add url_template column if it doesn't exist
+        let _ = sqlx::query("ALTER TABLE feeds ADD COLUMN url_template TEXT")
+            .execute(&self.pool)
+            .await;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS items (
@@ -87,61 +422,397 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_items_published ON items(published DESC)")
+            .execute(&self.pool)
+            .await?;
+
+        // Migration: add revision_count column if it doesn't exist
+        let _ = sqlx::query("ALTER TABLE items ADD COLUMN revision_count INTEGER NOT NULL DEFAULT 0")
+            .execute(&self.pool)
+            .await;
+
+        // Migration: add original_link column if it doesn't exist
+        let _ = sqlx::query("ALTER TABLE items ADD COLUMN original_link TEXT")
+            .execute(&self.pool)
+            .await;
+
+        // Migration: add reading state columns if they don't exist
+        let _ = sqlx::query("ALTER TABLE items ADD COLUMN is_read INTEGER NOT NULL DEFAULT 0")
+            .execute(&self.pool)
+            .await;
+        let _ = sqlx::query("ALTER TABLE items ADD COLUMN is_saved INTEGER NOT NULL DEFAULT 0")
+            .execute(&self.pool)
+            .await;
+
+        // Migration: add inserted_at column if it doesn't exist; items stored before
+        // it existed count as inserted when it was added
+        let _ = sqlx::query("ALTER TABLE items ADD COLUMN inserted_at TEXT")
+            .execute(&self.pool)
+            .await;
+        sqlx::query("UPDATE items SET inserted_at = ? WHERE inserted_at IS NULL")
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS item_revisions (
+                id INTEGER PRIMARY KEY,
+                item_id INTEGER NOT NULL REFERENCES items(id) ON DELETE CASCADE,
+                title TEXT NOT NULL,
+                link TEXT NOT NULL,
+                published TEXT,
+                revised_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_item_revisions_item
+            ON item_revisions(item_id)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS fetch_log (
+                id INTEGER PRIMARY KEY,
+                feed_id INTEGER NOT NULL REFERENCES feeds(id) ON DELETE CASCADE,
+                started_at TEXT NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                http_status INTEGER,
+                bytes INTEGER,
+                new_items INTEGER NOT NULL DEFAULT 0,
+                updated_items INTEGER NOT NULL DEFAULT 0,
+                filtered_items INTEGER NOT NULL DEFAULT 0,
+                error_category TEXT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Migration: add filtered_items column if it doesn't exist
+        let _ = sqlx::query(
+            "ALTER TABLE fetch_log ADD COLUMN filtered_items INTEGER NOT NULL DEFAULT 0",
+        )
+        .execute(&self.pool)
+        .await;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS fetch_log_filters (
+                fetch_id INTEGER NOT NULL REFERENCES fetch_log(id) ON DELETE CASCADE,
+                rule TEXT NOT NULL,
+                count INTEGER NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_fetch_log_feed_started
+            ON fetch_log(feed_id, started_at)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS secrets (
+                name TEXT PRIMARY KEY,
+                value BLOB NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
         Ok(())
     }
 
-    pub async fn sync_feeds(&self, configs: &[FeedConfig]) -> anyhow::Result<()> {
-        for config in configs {
+    /// The secret called `name`, created from `generate` the first time it's asked for
+    pub async fn get_or_create_secret(
+        &self,
+        name: &str,
+        generate: impl FnOnce() -> Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>> {
+        sqlx::query("INSERT INTO secrets (name, value) VALUES (?, ?) ON CONFLICT(name) DO NOTHING")
+            .bind(name)
+            .bind(generate())
+            .execute(&self.pool)
+            .await?;
+        let value = sqlx::query_scalar("SELECT value FROM secrets WHERE name = ?")
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(value)
+    }
+
+    /// Insert or update the configured feeds, all in one transaction. Their order
+    /// in `configs` becomes their dashboard order; feeds known only to the database
+    /// come after them. Feeds marked `disabled` keep their items but are left out
+    /// of fetching, the dashboard and the APIs until they are enabled again.
+    ///
+    /// Feeds synced before (they have a position) that are missing from `configs`
+    /// have left the config, and are disabled the same way. Feeds only ever added
+    /// to the database directly, by an OPML merge or an import, are left alone.
+    /// Returns how many feeds left the config.
+    pub async fn sync_feeds(&self, configs: &[FeedConfig]) -> anyhow::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        for (position, config) in configs.iter().enumerate() {
             sqlx::query(
                 r#"
-                INSERT INTO feeds (name, url, has_discussion)
-                VALUES (?, ?, ?)
+                INSERT INTO feeds
+                    (name, url, has_discussion, group_name, position, disabled, url_template)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
                 ON CONFLICT(url) DO UPDATE SET
                     name = excluded.name,
-                    has_discussion = excluded.has_discussion
+                    has_discussion = excluded.has_discussion,
+                    group_name = excluded.group_name,
+                    position = excluded.position,
+                    disabled = excluded.disabled,
+                    url_template = excluded.url_template
                 "#,
             )
             .bind(&config.name)
             .bind(&config.url)
             .bind(config.has_discussion)
-            .execute(&self.pool)
+            .bind(&config.group)
+            .bind(position as i64)
+            .bind(config.disabled)
+            .bind(&config.url_template)
+            .execute(&mut *tx)
             .await?;
         }
-        Ok(())
+
+        let sql = format!(
+            r#"
+            UPDATE feeds SET disabled = 1
+            WHERE position IS NOT NULL AND disabled = 0 AND url NOT IN ({})
+            "#,
+            vec!["?"; configs.len()].join(", ")
+        );
+        let mut query = sqlx::query(&sql);
+        for config in configs {
+            query = query.bind(&config.url);
+        }
+        let left = query.execute(&mut *tx).await?.rows_affected();
+
+        tx.commit().await?;
+        Ok(left)
+    }
+
+    /// Add feeds whose URL isn't known yet, leaving existing feeds untouched.
+    /// Returns how many were added.
+    pub async fn merge_feeds(&self, feeds: &[OpmlFeed]) -> anyhow::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let mut added = 0;
+
+        for feed in feeds {
+            added += sqlx::query(
+                r#"
+                INSERT INTO feeds (name, url, has_discussion, group_name, homepage_url)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT(url) DO NOTHING
+                "#,
+            )
+            .bind(&feed.title)
+            .bind(&feed.xml_url)
+            .bind(false)
+            .bind(&feed.group)
+            .bind(&feed.html_url)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(added)
     }
 
+    /// Delete the feeds with the given URLs together with their items and history
+    pub async fn remove_feeds(&self, urls: &[&str]) -> anyhow::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let mut removed = 0;
+
+        for url in urls {
+            // Items don't cascade, revisions and the fetch log do
+            sqlx::query("DELETE FROM items WHERE feed_id IN (SELECT id FROM feeds WHERE url = ?)")
+                .bind(url)
+                .execute(&mut *tx)
+                .await?;
+            removed += sqlx::query("DELETE FROM feeds WHERE url = ?")
+                .bind(url)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    /// Every feed that isn't disabled, in dashboard order
     pub async fn get_all_feeds(&self) -> anyhow::Result<Vec<Feed>> {
-        let feeds = sqlx::query_as::<_, Feed>("SELECT * FROM feeds ORDER BY id")
-            .fetch_all(&self.pool)
-            .await?;
+        self.get_feeds(false).await
+    }
+
+    /// Feeds that are disabled, with their items kept
+    pub async fn get_disabled_feeds(&self) -> anyhow::Result<Vec<Feed>> {
+        self.get_feeds(true).await
+    }
+
+    async fn get_feeds(&self, disabled: bool) -> anyhow::Result<Vec<Feed>> {
+        let feeds = sqlx::query_as::<_, Feed>(
+            "SELECT * FROM feeds WHERE disabled = ? ORDER BY position NULLS LAST, id",
+        )
+        .bind(disabled)
+        .fetch_all(&self.pool)
+        .await?;
         Ok(feeds)
     }
 
+    /// A feed that isn't disabled
     pub async fn get_feed(&self, feed_id: i64) -> anyhow::Result<Option<Feed>> {
-        let feed = sqlx::query_as::<_, Feed>("SELECT * FROM feeds WHERE id = ?")
+        let feed = sqlx::query_as::<_, Feed>("SELECT * FROM feeds WHERE id = ? AND disabled = 0")
             .bind(feed_id)
             .fetch_optional(&self.pool)
             .await?;
         Ok(feed)
     }
 
+    /// Fetch up to `limit` items for a feed, newest first.
+    /// When `after` is given, only items that sort strictly after that cursor are returned.
     pub async fn get_items_for_feed(
         &self,
         feed_id: i64,
         limit: i64,
-        offset: i64,
+        after: Option<&ItemCursor>,
+    ) -> anyhow::Result<Vec<Item>> {
+        self.get_recent_items_for_feed(feed_id, limit, after, None).await
+    }
+
+    /// Like `get_items_for_feed`, but when `published_since` is given, items
+    /// published before it are left out. Undated items are always included.
+    pub async fn get_recent_items_for_feed(
+        &self,
+        feed_id: i64,
+        limit: i64,
+        after: Option<&ItemCursor>,
+        published_since: Option<DateTime<Utc>>,
     ) -> anyhow::Result<Vec<Item>> {
+        let age = match published_since {
+            None => "",
+            Some(_) => "AND (published >= ? OR published IS NULL)",
+        };
+        let sql = format!(
+            r#"
+            SELECT * FROM items
+            WHERE feed_id = ? {} {}
+            ORDER BY published DESC NULLS LAST, id DESC
+            LIMIT ?
+            "#,
+            cursor_condition(after),
+            age
+        );
+
+        let mut query = bind_cursor(sqlx::query_as::<_, Item>(&sql).bind(feed_id), after);
+        if let Some(since) = published_since {
+            query = query.bind(since.to_rfc3339());
+        }
+        let items = query.bind(limit).fetch_all(&self.pool).await?;
+        Ok(items)
+    }
+
+    /// Fetch up to `limit` items of one feed, or across all feeds, newest first,
+    /// paged like `get_items_for_feed`. When `published_since` is given, only items
+    /// published at or after it are returned; undated items are left out.
+    pub async fn get_recent_items(
+        &self,
+        feed_id: Option<i64>,
+        limit: i64,
+        after: Option<&ItemCursor>,
+        published_since: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<Item>> {
+        let feed = match feed_id {
+            None => "",
+            Some(_) => "AND feed_id = ?",
+        };
+        let age = match published_since {
+            None => "",
+            Some(_) => "AND published >= ?",
+        };
+        let sql = format!(
+            r#"
+            SELECT * FROM items
+            WHERE {} {} {} {}
+            ORDER BY published DESC NULLS LAST, id DESC
+            LIMIT ?
+            "#,
+            ENABLED_ITEMS,
+            feed,
+            cursor_condition(after),
+            age
+        );
+
+        let mut query = sqlx::query_as::<_, Item>(&sql);
+        if let Some(feed_id) = feed_id {
+            query = query.bind(feed_id);
+        }
+        let mut query = bind_cursor(query, after);
+        if let Some(since) = published_since {
+            query = query.bind(since.to_rfc3339());
+        }
+        let items = query.bind(limit).fetch_all(&self.pool).await?;
+        Ok(items)
+    }
+
+    /// The newest `limit` items across `feed_ids`, in the order of `get_recent_items`
+    pub async fn get_recent_items_for_feeds(
+        &self,
+        feed_ids: &[i64],
+        limit: i64,
+    ) -> anyhow::Result<Vec<Item>> {
+        if feed_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            r#"
+            SELECT * FROM items
+            WHERE {} AND feed_id IN ({})
+            ORDER BY published DESC NULLS LAST, id DESC
+            LIMIT ?
+            "#,
+            ENABLED_ITEMS,
+            vec!["?"; feed_ids.len()].join(", ")
+        );
+        let mut query = sqlx::query_as::<_, Item>(&sql);
+        for feed_id in feed_ids {
+            query = query.bind(feed_id);
+        }
+        Ok(query.bind(limit).fetch_all(&self.pool).await?)
+    }
+
+    /// Fetch every item for a feed, newest first
+    pub async fn get_all_items_for_feed(&self, feed_id: i64) -> anyhow::Result<Vec<Item>> {
         let items = sqlx::query_as::<_, Item>(
             r#"
             SELECT * FROM items
             WHERE feed_id = ?
             ORDER BY published DESC NULLS LAST, id DESC
-            LIMIT ? OFFSET ?
             "#,
         )
         .bind(feed_id)
-        .bind(limit)
-        .bind(offset)
         .fetch_all(&self.pool)
         .await?;
         Ok(items)
@@ -155,40 +826,555 @@ impl Database {
         Ok(count.0)
     }
 
+    /// Item counts of every feed that has items, in one query
+    pub async fn get_item_counts(&self) -> anyhow::Result<HashMap<i64, i64>> {
+        let counts: Vec<(i64, i64)> =
+            sqlx::query_as("SELECT feed_id, COUNT(*) FROM items GROUP BY feed_id")
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(counts.into_iter().collect())
+    }
+
+    pub async fn get_item(&self, item_id: i64) -> anyhow::Result<Option<Item>> {
+        let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(item)
+    }
+
+    /// Up to `limit` items with an id above `after`, lowest id first
+    pub async fn get_items_after_id(&self, after: i64, limit: i64) -> anyhow::Result<Vec<Item>> {
+        let sql = format!(
+            "SELECT * FROM items WHERE {} AND id > ? ORDER BY id LIMIT ?",
+            ENABLED_ITEMS
+        );
+        let items = sqlx::query_as::<_, Item>(&sql)
+            .bind(after)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(items)
+    }
+
+    /// Up to `limit` items with an id below `before`, highest id first
+    pub async fn get_items_before_id(&self, before: i64, limit: i64) -> anyhow::Result<Vec<Item>> {
+        let sql = format!(
+            "SELECT * FROM items WHERE {} AND id < ? ORDER BY id DESC LIMIT ?",
+            ENABLED_ITEMS
+        );
+        let items = sqlx::query_as::<_, Item>(&sql)
+            .bind(before)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(items)
+    }
+
+    /// The items among `ids` that exist, lowest id first
+    pub async fn get_items_by_ids(&self, ids: &[i64]) -> anyhow::Result<Vec<Item>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT * FROM items WHERE {} AND id IN ({}) ORDER BY id",
+            ENABLED_ITEMS,
+            vec!["?"; ids.len()].join(", ")
+        );
+        let mut query = sqlx::query_as::<_, Item>(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+        Ok(query.fetch_all(&self.pool).await?)
+    }
+
+    pub async fn get_total_item_count(&self) -> anyhow::Result<i64> {
+        let sql = format!("SELECT COUNT(*) FROM items WHERE {}", ENABLED_ITEMS);
+        let count: (i64,) = sqlx::query_as(&sql).fetch_one(&self.pool).await?;
+        Ok(count.0)
+    }
+
+    /// Ids of every unread item, lowest first
+    pub async fn get_unread_item_ids(&self) -> anyhow::Result<Vec<i64>> {
+        let sql = format!(
+            "SELECT id FROM items WHERE {} AND is_read = 0 ORDER BY id",
+            ENABLED_ITEMS
+        );
+        let ids = sqlx::query_scalar(&sql).fetch_all(&self.pool).await?;
+        Ok(ids)
+    }
+
+    /// Ids of every saved item, lowest first
+    pub async fn get_saved_item_ids(&self) -> anyhow::Result<Vec<i64>> {
+        let sql = format!(
+            "SELECT id FROM items WHERE {} AND is_saved = 1 ORDER BY id",
+            ENABLED_ITEMS
+        );
+        let ids = sqlx::query_scalar(&sql).fetch_all(&self.pool).await?;
+        Ok(ids)
+    }
+
+    /// Mark items read or unread. Returns how many exist.
+    pub async fn set_items_read(&self, ids: &[i64], read: bool) -> anyhow::Result<u64> {
+        self.set_item_flag("is_read", ids, read).await
+    }
+
+    /// Save or unsave items. Returns how many exist.
+    pub async fn set_items_saved(&self, ids: &[i64], saved: bool) -> anyhow::Result<u64> {
+        self.set_item_flag("is_saved", ids, saved).await
+    }
+
+    async fn set_item_flag(&self, column: &str, ids: &[i64], value: bool) -> anyhow::Result<u64> {
+        let sql = format!("UPDATE items SET {} = ? WHERE id = ?", column);
+        let mut tx = self.pool.begin().await?;
+        let mut updated = 0;
+        for id in ids {
+            updated += sqlx::query(&sql)
+                .bind(value)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    /// Items matching `filter`, highest id first unless `oldest_first` is set
+    pub async fn find_items(&self, filter: &ItemFilter) -> anyhow::Result<Vec<Item>> {
+        let mut conditions = vec![ENABLED_ITEMS.to_string()];
+        if let Some(feed_ids) = &filter.feed_ids {
+            if feed_ids.is_empty() {
+                return Ok(Vec::new());
+            }
+            conditions.push(format!("feed_id IN ({})", vec!["?"; feed_ids.len()].join(", ")));
+        }
+        if filter.is_read.is_some() {
+            conditions.push("is_read = ?".to_string());
+        }
+        if filter.is_saved.is_some() {
+            conditions.push("is_saved = ?".to_string());
+        }
+        if filter.published_since.is_some() {
+            conditions.push("(published >= ? OR published IS NULL)".to_string());
+        }
+        if filter.published_before.is_some() {
+            conditions.push("published < ?".to_string());
+        }
+        let order = if filter.oldest_first { "ASC" } else { "DESC" };
+        if filter.after_id.is_some() {
+            conditions.push(if filter.oldest_first { "id > ?" } else { "id < ?" }.to_string());
+        }
+        let sql = format!(
+            "SELECT * FROM items WHERE {} ORDER BY id {} LIMIT ?",
+            conditions.join(" AND "),
+            order
+        );
+
+        let mut query = sqlx::query_as::<_, Item>(&sql);
+        for feed_id in filter.feed_ids.iter().flatten() {
+            query = query.bind(feed_id);
+        }
+        if let Some(is_read) = filter.is_read {
+            query = query.bind(is_read);
+        }
+        if let Some(is_saved) = filter.is_saved {
+            query = query.bind(is_saved);
+        }
+        if let Some(since) = filter.published_since {
+            query = query.bind(since.to_rfc3339());
+        }
+        if let Some(before) = filter.published_before {
+            query = query.bind(before.to_rfc3339());
+        }
+        if let Some(after_id) = filter.after_id {
+            query = query.bind(after_id);
+        }
+        Ok(query.bind(filter.limit).fetch_all(&self.pool).await?)
+    }
+
+    /// Unread item count and newest unread publication date of every feed with
+    /// unread items
+    pub async fn get_unread_counts(&self) -> anyhow::Result<Vec<(i64, i64, Option<String>)>> {
+        let sql = format!(
+            r#"
+            SELECT feed_id, COUNT(*), MAX(published) FROM items
+            WHERE {} AND is_read = 0
+            GROUP BY feed_id
+            ORDER BY feed_id
+            "#,
+            ENABLED_ITEMS
+        );
+        let counts = sqlx::query_as(&sql).fetch_all(&self.pool).await?;
+        Ok(counts)
+    }
+
+    /// Mark the items of `feed_ids` read, or only those published at or before
+    /// `before`, along with undated ones. Returns how many were unread.
+    pub async fn mark_feeds_read(
+        &self,
+        feed_ids: &[i64],
+        before: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<u64> {
+        let age = match before {
+            None => "",
+            Some(_) => "AND (published <= ? OR published IS NULL)",
+        };
+        let sql = format!(
+            "UPDATE items SET is_read = 1 WHERE feed_id = ? AND is_read = 0 {}",
+            age
+        );
+        let mut tx = self.pool.begin().await?;
+        let mut marked = 0;
+        for feed_id in feed_ids {
+            let mut query = sqlx::query(&sql).bind(feed_id);
+            if let Some(before) = before {
+                query = query.bind(before.to_rfc3339());
+            }
+            marked += query.execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        Ok(marked)
+    }
+
+    /// Previous versions of an item, most recent edit first
+    pub async fn get_item_revisions(&self, item_id: i64) -> anyhow::Result<Vec<ItemRevision>> {
+        let revisions = sqlx::query_as::<_, ItemRevision>(
+            "SELECT * FROM item_revisions WHERE item_id = ? ORDER BY id DESC",
+        )
+        .bind(item_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(revisions)
+    }
+
+    /// Time of the latest edit to each of `item_ids` that has any
+    pub async fn get_last_revision_times(
+        &self,
+        item_ids: &[i64],
+    ) -> anyhow::Result<HashMap<i64, String>> {
+        if item_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let sql = format!(
+            "SELECT item_id, MAX(revised_at) FROM item_revisions WHERE item_id IN ({}) \
+             GROUP BY item_id",
+            vec!["?"; item_ids.len()].join(", ")
+        );
+        let mut query = sqlx::query_as::<_, (i64, String)>(&sql);
+        for id in item_ids {
+            query = query.bind(id);
+        }
+        Ok(query.fetch_all(&self.pool).await?.into_iter().collect())
+    }
+
+    /// Insert or update an item by (feed_id, guid).
+    /// `original_link` is the link as the feed gave it, when `[links]` settings cleaned
+    /// it up into `link`. When an existing item's title, link or published date changes,
+    /// the previous values are kept in `item_revisions`; a link that only reads
+    /// differently because the clean-up rules changed is not an edit.
+    #[allow(clippy::too_many_arguments)]
     pub async fn upsert_item(
         &self,
         feed_id: i64,
         guid: &str,
         title: &str,
         link: &str,
+        original_link: Option<&str>,
         discussion_link: Option<&str>,
         published: Option<DateTime<Utc>>,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<UpsertOutcome> {
         let published_str = published.map(|p| p.to_rfc3339());
+        let given_link = original_link.unwrap_or(link);
+
+        let mut tx = self.pool.begin().await?;
+
+        let existing: Option<(i64, String, String, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, title, link, COALESCE(original_link, link), published
+            FROM items WHERE feed_id = ? AND guid = ?
+            "#,
+        )
+        .bind(feed_id)
+        .bind(guid)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let outcome = match &existing {
+            None => UpsertOutcome::Inserted,
+            Some((item_id, old_title, old_link, old_given_link, old_published))
+                if old_title != title
+                    || old_given_link != given_link
+                    || *old_published != published_str =>
+            {
+                sqlx::query(
+                    r#"
+                    INSERT INTO item_revisions (item_id, title, link, published, revised_at)
+                    VALUES (?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(item_id)
+                .bind(old_title)
+                .bind(old_link)
+                .bind(old_published)
+                .bind(Utc::now().to_rfc3339())
+                .execute(&mut *tx)
+                .await?;
+                UpsertOutcome::Updated
+            }
+            Some(_) => UpsertOutcome::Unchanged,
+        };
 
         sqlx::query(
             r#"
-            INSERT INTO items (feed_id, guid, title, link, discussion_link, published)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO items
+                (feed_id, guid, title, link, original_link, discussion_link, published,
+                 inserted_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(feed_id, guid) DO UPDATE SET
                 title = excluded.title,
                 link = excluded.link,
+                original_link = excluded.original_link,
                 discussion_link = excluded.discussion_link,
-                published = excluded.published
+                published = excluded.published,
+                revision_count = revision_count + ?
             "#,
         )
         .bind(feed_id)
         .bind(guid)
         .bind(title)
         .bind(link)
+        .bind(original_link)
         .bind(discussion_link)
         .bind(published_str)
-        .execute(&self.pool)
+        .bind(Utc::now().to_rfc3339())
+        .bind((outcome == UpsertOutcome::Updated) as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
+    /// Log a fetch attempt, with how many entries each filter rule dropped
+    pub async fn record_fetch(
+        &self,
+        record: &FetchRecord,
+        filtered: &[(String, i64)],
+    ) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let (fetch_id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO fetch_log
+                (feed_id, started_at, duration_ms, http_status, bytes,
+                 new_items, updated_items, filtered_items, error_category)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING id
+            "#,
+        )
+        .bind(record.feed_id)
+        .bind(&record.started_at)
+        .bind(record.duration_ms)
+        .bind(record.http_status)
+        .bind(record.bytes)
+        .bind(record.new_items)
+        .bind(record.updated_items)
+        .bind(record.filtered_items)
+        .bind(&record.error_category)
+        .fetch_one(&mut *tx)
         .await?;
 
+        for (rule, count) in filtered {
+            sqlx::query("INSERT INTO fetch_log_filters (fetch_id, rule, count) VALUES (?, ?, ?)")
+                .bind(fetch_id)
+                .bind(rule)
+                .bind(count)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 
+    /// Fetch attempts for a feed started at or after `since`, oldest first
+    pub async fn get_fetch_log(
+        &self,
+        feed_id: i64,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<FetchRecord>> {
+        let records = sqlx::query_as::<_, FetchRecord>(
+            r#"
+            SELECT feed_id, started_at, duration_ms, http_status, bytes,
+                   new_items, updated_items, filtered_items, error_category
+            FROM fetch_log
+            WHERE feed_id = ? AND started_at >= ?
+            ORDER BY started_at
+            "#,
+        )
+        .bind(feed_id)
+        .bind(since.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(records)
+    }
+
+    /// Entries dropped per filter rule for a feed since `since`, most first
+    pub async fn get_filter_counts(
+        &self,
+        feed_id: i64,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<(String, i64)>> {
+        let counts = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT f.rule, SUM(f.count) AS total
+            FROM fetch_log_filters f
+            JOIN fetch_log l ON l.id = f.fetch_id
+            WHERE l.feed_id = ? AND l.started_at >= ?
+            GROUP BY f.rule
+            ORDER BY total DESC, f.rule
+            "#,
+        )
+        .bind(feed_id)
+        .bind(since.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(counts)
+    }
+
+    /// Drop fetch log entries older than `FETCH_LOG_RETENTION_DAYS`
+    pub async fn prune_fetch_log(&self) -> anyhow::Result<u64> {
+        let cutoff = Utc::now() - chrono::Duration::days(FETCH_LOG_RETENTION_DAYS);
+        let result = sqlx::query("DELETE FROM fetch_log WHERE started_at < ?")
+            .bind(cutoff.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Number of items for a feed published at or after `since`
+    pub async fn count_items_published_since(
+        &self,
+        feed_id: i64,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<i64> {
+        let count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM items WHERE feed_id = ? AND published >= ?")
+                .bind(feed_id)
+                .bind(since.to_rfc3339())
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(count.0)
+    }
+
+    /// Publication date of a feed's newest item
+    pub async fn get_latest_published(&self, feed_id: i64) -> anyhow::Result<Option<String>> {
+        let latest: (Option<String>,) =
+            sqlx::query_as("SELECT MAX(published) FROM items WHERE feed_id = ?")
+                .bind(feed_id)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(latest.0)
+    }
+
+    /// Insert feeds and their items from an export, all in one transaction
+    pub async fn restore_feeds(&self, feeds: &[FeedSnapshot]) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for feed in feeds {
+            let (feed_id,): (i64,) = sqlx::query_as(
+                r#"
+                INSERT INTO feeds
+                    (name, url, has_discussion, last_fetched, last_error, homepage_url, group_name,
+                     disabled)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                RETURNING id
+                "#,
+            )
+            .bind(&feed.name)
+            .bind(&feed.url)
+            .bind(feed.has_discussion)
+            .bind(&feed.last_fetched)
+            .bind(&feed.last_error)
+            .bind(&feed.homepage_url)
+            .bind(&feed.group_name)
+            .bind(feed.disabled)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            for item in &feed.items {
+                let (item_id,): (i64,) = sqlx::query_as(
+                    r#"
+                    INSERT INTO items
+                        (feed_id, guid, title, link, original_link, discussion_link, published,
+                         is_read, is_saved, revision_count, inserted_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    RETURNING id
+                    "#,
+                )
+                .bind(feed_id)
+                .bind(&item.guid)
+                .bind(&item.title)
+                .bind(&item.link)
+                .bind(&item.original_link)
+                .bind(&item.discussion_link)
+                .bind(&item.published)
+                .bind(item.is_read)
+                .bind(item.is_saved)
+                .bind(item.revisions.len() as i64)
+                .bind(Utc::now().to_rfc3339())
+                .fetch_one(&mut *tx)
+                .await?;
+
+                // Revisions are exported newest first; insert oldest first to keep id order
+                for revision in item.revisions.iter().rev() {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO item_revisions (item_id, title, link, published, revised_at)
+                        VALUES (?, ?, ?, ?, ?)
+                        "#,
+                    )
+                    .bind(item_id)
+                    .bind(&revision.title)
+                    .bind(&revision.link)
+                    .bind(&revision.published)
+                    .bind(&revision.revised_at)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Write a consistent copy of the live database to `path` using `VACUUM INTO`.
+    /// The target file must not already exist.
+    pub async fn backup_to(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        if path.exists() {
+            anyhow::bail!("Backup target already exists: {}", path.display());
+        }
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy().as_ref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Run `PRAGMA integrity_check`, returning the problems found (empty when healthy)
+    pub async fn integrity_check(&self) -> anyhow::Result<Vec<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as("PRAGMA integrity_check")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(row,)| row)
+            .filter(|row| row != "ok")
+            .collect())
+    }
+
     pub async fn update_feed_fetched(
         &self,
         feed_id: i64,
@@ -229,6 +1415,7 @@ mod tests {
             name: name.to_string(),
             url: url.to_string(),
             has_discussion,
+            ..Default::default()
         }
     }
 
@@ -250,6 +1437,40 @@ mod tests {
             assert!(feeds.is_empty());
         }
 
+        #[tokio::test]
+        async fn test_connect_applies_pragmas() {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let url = format!(
+                "sqlite:{}?mode=rwc",
+                temp_dir.path().join("test.db").display()
+            );
+            let config = DatabaseConfig {
+                max_connections: 3,
+                busy_timeout_ms: 1234,
+                ..Default::default()
+            };
+
+            let db = Database::connect(&url, &config).await.unwrap();
+            let pragmas = db.effective_pragmas().await.unwrap();
+
+            assert_eq!(pragmas.journal_mode, "wal");
+            assert_eq!(pragmas.synchronous, 1); // NORMAL
+            assert_eq!(pragmas.busy_timeout_ms, 1234);
+            assert!(pragmas.foreign_keys);
+            assert_eq!(pragmas.max_connections, 3);
+        }
+
+        #[tokio::test]
+        async fn test_connect_rejects_invalid_journal_mode() {
+            let config = DatabaseConfig {
+                journal_mode: "sideways".to_string(),
+                ..Default::default()
+            };
+
+            let result = Database::connect("sqlite::memory:", &config).await;
+            assert!(result.is_err());
+        }
+
         #[tokio::test]
         async fn test_double_initialization_is_safe() {
             let db = create_test_db().await;
@@ -332,9 +1553,120 @@ mod tests {
             let feeds = db.get_all_feeds().await.unwrap();
             assert!(feeds.is_empty());
         }
+
+        #[tokio::test]
+        async fn test_sync_follows_config_order() {
+            let db = create_test_db().await;
+            let a = create_feed_config("A", "https://a.com/rss", false);
+            let b = create_feed_config("B", "https://b.com/rss", false);
+            db.sync_feeds(&[a.clone(), b.clone()]).await.unwrap();
+
+            db.sync_feeds(&[b, a]).await.unwrap();
+
+            let names: Vec<String> =
+                db.get_all_feeds().await.unwrap().into_iter().map(|f| f.name).collect();
+            assert_eq!(names, vec!["B", "A"]);
+        }
+
+        #[tokio::test]
+        async fn test_disabled_feed_keeps_its_items() {
+            let db = create_test_db().await;
+            let mut feed = create_feed_config("A", "https://a.com/rss", false);
+            db.sync_feeds(&[feed.clone()]).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+            db.upsert_item(feed_id, "1", "One", "https://a.com/1", None, None, None)
+                .await
+                .unwrap();
+
+            feed.disabled = true;
+            db.sync_feeds(&[feed.clone()]).await.unwrap();
+            assert!(db.get_all_feeds().await.unwrap().is_empty());
+            assert_eq!(db.get_disabled_feeds().await.unwrap()[0].id, feed_id);
+            assert!(db.get_feed(feed_id).await.unwrap().is_none());
+            assert!(db.get_unread_item_ids().await.unwrap().is_empty());
+            assert!(db.get_recent_items(None, 10, None, None).await.unwrap().is_empty());
+
+            feed.disabled = false;
+            db.sync_feeds(&[feed]).await.unwrap();
+            assert_eq!(db.get_unread_item_ids().await.unwrap().len(), 1);
+        }
+    }
+
+    // Get feed tests
+    mod merge_feeds_tests {
+        use super::*;
+
+        fn create_opml_feed(title: &str, url: &str, group: Option<&str>) -> OpmlFeed {
+            OpmlFeed {
+                title: title.to_string(),
+                xml_url: url.to_string(),
+                html_url: Some("https://home.example.com".to_string()),
+                group: group.map(|g| g.to_string()),
+            }
+        }
+
+        #[tokio::test]
+        async fn test_merge_adds_only_new_feeds() {
+            let db = create_test_db().await;
+            db.sync_feeds(&[create_feed_config("HN", "https://hn.com/rss", true)])
+                .await
+                .unwrap();
+
+            let added = db
+                .merge_feeds(&[
+                    create_opml_feed("Hacker News", "https://hn.com/rss", None),
+                    create_opml_feed("Ars", "https://ars.com/rss", Some("Tech")),
+                ])
+                .await
+                .unwrap();
+
+            assert_eq!(added, 1);
+            let feeds = db.get_all_feeds().await.unwrap();
+            assert_eq!(feeds.len(), 2);
+            // Existing feed keeps its settings
+            assert_eq!(feeds[0].name, "HN");
+            assert!(feeds[0].has_discussion);
+            assert_eq!(feeds[1].group_name.as_deref(), Some("Tech"));
+            assert_eq!(feeds[1].homepage_url.as_deref(), Some("https://home.example.com"));
+        }
+    }
+
+    mod remove_feeds_tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_remove_feeds_deletes_items() {
+            let db = create_test_db().await;
+            db.sync_feeds(&[
+                create_feed_config("Keep", "https://keep.com/rss", false),
+                create_feed_config("Drop", "https://drop.com/rss", false),
+            ])
+            .await
+            .unwrap();
+            for feed_id in [1, 2] {
+                db.upsert_item(feed_id, "guid", "Title", "https://link.com", None, None, None)
+                    .await
+                    .unwrap();
+            }
+
+            let removed = db.remove_feeds(&["https://drop.com/rss"]).await.unwrap();
+
+            assert_eq!(removed, 1);
+            let feeds = db.get_all_feeds().await.unwrap();
+            assert_eq!(feeds.len(), 1);
+            assert_eq!(feeds[0].name, "Keep");
+            assert_eq!(db.get_item_count_for_feed(2).await.unwrap(), 0);
+            assert_eq!(db.get_item_count_for_feed(1).await.unwrap(), 1);
+        }
+
+        #[tokio::test]
+        async fn test_remove_unknown_feed() {
+            let db = create_test_db().await;
+            let removed = db.remove_feeds(&["https://unknown.com/rss"]).await.unwrap();
+            assert_eq!(removed, 0);
+        }
     }
 
-    // Get feed tests
     mod get_feed_tests {
         use super::*;
 
@@ -378,13 +1710,14 @@ mod tests {
                 "guid-123",
                 "Test Title",
                 "https://article.com",
+                None,
                 Some("https://comments.com"),
                 Some(Utc::now()),
             )
             .await
             .unwrap();
 
-            let items = db.get_items_for_feed(feed_id, 10, 0).await.unwrap();
+            let items = db.get_items_for_feed(feed_id, 10, None).await.unwrap();
             assert_eq!(items.len(), 1);
             assert_eq!(items[0].title, "Test Title");
             assert_eq!(items[0].link, "https://article.com");
@@ -410,11 +1743,12 @@ mod tests {
                 "https://article.com",
                 None,
                 None,
+                None,
             )
             .await
             .unwrap();
 
-            let items = db.get_items_for_feed(feed_id, 10, 0).await.unwrap();
+            let items = db.get_items_for_feed(feed_id, 10, None).await.unwrap();
             assert_eq!(items.len(), 1);
             assert!(items[0].discussion_link.is_none());
             assert!(items[0].published.is_none());
@@ -437,6 +1771,7 @@ mod tests {
                 "https://original.com",
                 None,
                 None,
+                None,
             )
             .await
             .unwrap();
@@ -447,18 +1782,259 @@ mod tests {
                 "guid-123",
                 "Updated Title",
                 "https://updated.com",
+                None,
                 Some("https://comments.com"),
                 Some(Utc::now()),
             )
             .await
             .unwrap();
 
-            let items = db.get_items_for_feed(feed_id, 10, 0).await.unwrap();
+            let items = db.get_items_for_feed(feed_id, 10, None).await.unwrap();
             assert_eq!(items.len(), 1);
             assert_eq!(items[0].title, "Updated Title");
             assert_eq!(items[0].link, "https://updated.com");
         }
 
+        #[tokio::test]
+        async fn test_upsert_reports_outcome() {
+            let db = create_test_db().await;
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", false)];
+            db.sync_feeds(&configs).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+
+            let first = db
+                .upsert_item(feed_id, "guid-1", "Title", "https://a.com", None, None, None)
+                .await
+                .unwrap();
+            let same = db
+                .upsert_item(feed_id, "guid-1", "Title", "https://a.com", None, None, None)
+                .await
+                .unwrap();
+            let changed = db
+                .upsert_item(feed_id, "guid-1", "New Title", "https://a.com", None, None, None)
+                .await
+                .unwrap();
+
+            assert_eq!(first, UpsertOutcome::Inserted);
+            assert_eq!(same, UpsertOutcome::Unchanged);
+            assert_eq!(changed, UpsertOutcome::Updated);
+        }
+
+        #[tokio::test]
+        async fn test_link_clean_up_is_not_a_revision() {
+            let db = create_test_db().await;
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", false)];
+            db.sync_feeds(&configs).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+            let given = "https://a.com/?utm_source=x";
+            db.upsert_item(feed_id, "guid-1", "Title", given, None, None, None)
+                .await
+                .unwrap();
+
+            // Clean-up rules added: the stored link changes, the feed's didn't
+            let outcome = db
+                .upsert_item(feed_id, "guid-1", "Title", "https://a.com/", Some(given), None, None)
+                .await
+                .unwrap();
+            assert_eq!(outcome, UpsertOutcome::Unchanged);
+            let item = &db.get_all_items_for_feed(feed_id).await.unwrap()[0];
+            assert_eq!(item.link, "https://a.com/");
+            assert_eq!(item.original_link.as_deref(), Some(given));
+            assert_eq!(item.revision_count, 0);
+
+            // The feed changing the link still is
+            let outcome = db
+                .upsert_item(feed_id, "guid-1", "Title", "https://b.com/", None, None, None)
+                .await
+                .unwrap();
+            assert_eq!(outcome, UpsertOutcome::Updated);
+            let item = &db.get_all_items_for_feed(feed_id).await.unwrap()[0];
+            assert_eq!(item.original_link, None);
+            let revisions = db.get_item_revisions(item.id).await.unwrap();
+            assert_eq!(revisions[0].link, "https://a.com/");
+        }
+
+        #[tokio::test]
+        async fn test_reading_state() {
+            let db = create_test_db().await;
+            let configs = vec![
+                create_feed_config("Test", "https://test.com/rss", false),
+                create_feed_config("Other", "https://other.com/rss", false),
+            ];
+            db.sync_feeds(&configs).await.unwrap();
+            let feeds = db.get_all_feeds().await.unwrap();
+            let (test, other) = (feeds[0].id, feeds[1].id);
+            let old = Some(Utc::now() - chrono::Duration::days(2));
+            db.upsert_item(test, "old", "Old", "https://a.com/1", None, None, old)
+                .await
+                .unwrap();
+            db.upsert_item(test, "new", "New", "https://a.com/2", None, None, Some(Utc::now()))
+                .await
+                .unwrap();
+            db.upsert_item(other, "x", "Other", "https://b.com/", None, None, None)
+                .await
+                .unwrap();
+            let items = db.get_items_after_id(0, 10).await.unwrap();
+            let ids: Vec<i64> = items.iter().map(|i| i.id).collect();
+
+            assert_eq!(db.set_items_read(&[ids[2], 999], true).await.unwrap(), 1);
+            db.set_items_saved(&[ids[0]], true).await.unwrap();
+            assert_eq!(db.get_unread_item_ids().await.unwrap(), [ids[0], ids[1]]);
+            assert_eq!(db.get_saved_item_ids().await.unwrap(), [ids[0]]);
+
+            // A refresh doesn't reset the state
+            db.upsert_item(test, "old", "Old, edited", "https://a.com/1", None, None, old)
+                .await
+                .unwrap();
+            let item = db.get_item(ids[0]).await.unwrap().unwrap();
+            assert!(item.is_saved && !item.is_read);
+
+            let yesterday = Utc::now() - chrono::Duration::days(1);
+            assert_eq!(db.mark_feeds_read(&[test], Some(yesterday)).await.unwrap(), 1);
+            assert_eq!(db.get_unread_item_ids().await.unwrap(), [ids[1]]);
+            db.set_items_read(&[ids[2]], false).await.unwrap();
+            assert_eq!(db.mark_feeds_read(&[test, other], None).await.unwrap(), 2);
+            assert!(db.get_unread_item_ids().await.unwrap().is_empty());
+
+            let items = db.get_items_before_id(ids[2], 10).await.unwrap();
+            assert_eq!(items.iter().map(|i| i.id).collect::<Vec<_>>(), [ids[1], ids[0]]);
+            let items = db.get_items_by_ids(&[ids[2], ids[0], 999]).await.unwrap();
+            assert_eq!(items.iter().map(|i| i.id).collect::<Vec<_>>(), [ids[0], ids[2]]);
+            assert_eq!(db.get_total_item_count().await.unwrap(), 3);
+        }
+
+        #[tokio::test]
+        async fn test_find_items_and_unread_counts() {
+            let db = create_test_db().await;
+            let configs = vec![
+                create_feed_config("Test", "https://test.com/rss", false),
+                create_feed_config("Other", "https://other.com/rss", false),
+            ];
+            db.sync_feeds(&configs).await.unwrap();
+            let feeds = db.get_all_feeds().await.unwrap();
+            let (test, other) = (feeds[0].id, feeds[1].id);
+            for (feed, guid, hours) in [(test, "a", 30), (other, "b", 20), (test, "c", 10)] {
+                let published = Utc::now() - chrono::Duration::hours(hours);
+                let link = format!("https://{}.com/", guid);
+                db.upsert_item(feed, guid, guid, &link, None, None, Some(published))
+                    .await
+                    .unwrap();
+            }
+            db.upsert_item(test, "d", "d", "https://d.com/", None, None, None)
+                .await
+                .unwrap();
+            let items = db.get_items_after_id(0, 10).await.unwrap();
+            let ids: Vec<i64> = items.iter().map(|i| i.id).collect();
+            db.set_items_read(&[ids[0]], true).await.unwrap();
+            db.set_items_saved(&[ids[1], ids[2]], true).await.unwrap();
+
+            let find = |filter: ItemFilter| {
+                let db = &db;
+                async move {
+                    let items = db.find_items(&ItemFilter { limit: 10, ..filter }).await.unwrap();
+                    items.iter().map(|i| i.title.clone()).collect::<Vec<_>>()
+                }
+            };
+            assert_eq!(find(ItemFilter::default()).await, ["d", "c", "b", "a"]);
+            let oldest_first = ItemFilter {
+                oldest_first: true,
+                after_id: Some(ids[1]),
+                ..Default::default()
+            };
+            assert_eq!(find(oldest_first).await, ["c", "d"]);
+            let unread_of_test = ItemFilter {
+                feed_ids: Some(vec![test]),
+                is_read: Some(false),
+                ..Default::default()
+            };
+            assert_eq!(find(unread_of_test).await, ["d", "c"]);
+            let saved = ItemFilter {
+                is_saved: Some(true),
+                published_before: Some(Utc::now() - chrono::Duration::hours(15)),
+                ..Default::default()
+            };
+            assert_eq!(find(saved).await, ["b"]);
+            let recent = ItemFilter {
+                published_since: Some(Utc::now() - chrono::Duration::hours(25)),
+                after_id: Some(ids[3]),
+                ..Default::default()
+            };
+            assert_eq!(find(recent).await, ["c", "b"]);
+            let recent_or_undated = ItemFilter {
+                published_since: Some(Utc::now() - chrono::Duration::hours(25)),
+                ..Default::default()
+            };
+            assert_eq!(find(recent_or_undated).await, ["d", "c", "b"]);
+            let no_feeds = ItemFilter {
+                feed_ids: Some(Vec::new()),
+                ..Default::default()
+            };
+            assert!(find(no_feeds).await.is_empty());
+
+            let counts = db.get_unread_counts().await.unwrap();
+            assert_eq!(counts.len(), 2);
+            assert_eq!((counts[0].0, counts[0].1), (test, 2));
+            assert_eq!(counts[0].2, items[2].published);
+            assert_eq!((counts[1].0, counts[1].1), (other, 1));
+        }
+
+        #[tokio::test]
+        async fn test_upsert_records_revisions() {
+            let db = create_test_db().await;
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", false)];
+            db.sync_feeds(&configs).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+
+            db.upsert_item(feed_id, "guid-1", "Original", "https://a.com", None, None, None)
+                .await
+                .unwrap();
+            db.upsert_item(feed_id, "guid-1", "Rewritten", "https://a.com", None, None, None)
+                .await
+                .unwrap();
+            db.upsert_item(feed_id, "guid-1", "Rewritten", "https://b.com", None, None, None)
+                .await
+                .unwrap();
+
+            let item = &db.get_items_for_feed(feed_id, 10, None).await.unwrap()[0];
+            assert_eq!(item.revision_count, 2);
+
+            let revisions = db.get_item_revisions(item.id).await.unwrap();
+            assert_eq!(revisions.len(), 2);
+            // Most recent edit first, holding the values it replaced
+            assert_eq!(revisions[0].title, "Rewritten");
+            assert_eq!(revisions[0].link, "https://a.com");
+            assert_eq!(revisions[1].title, "Original");
+        }
+
+        #[tokio::test]
+        async fn test_discussion_link_change_is_not_a_revision() {
+            let db = create_test_db().await;
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", true)];
+            db.sync_feeds(&configs).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+
+            db.upsert_item(feed_id, "guid-1", "Title", "https://a.com", None, None, None)
+                .await
+                .unwrap();
+            let outcome = db
+                .upsert_item(
+                    feed_id,
+                    "guid-1",
+                    "Title",
+                    "https://a.com",
+                    None,
+                    Some("https://comments.com"),
+                    None,
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(outcome, UpsertOutcome::Unchanged);
+            let item = &db.get_items_for_feed(feed_id, 10, None).await.unwrap()[0];
+            assert_eq!(item.revision_count, 0);
+            assert_eq!(item.discussion_link.as_deref(), Some("https://comments.com"));
+        }
+
         #[tokio::test]
         async fn test_upsert_multiple_items() {
             let db = create_test_db().await;
@@ -476,12 +2052,13 @@ mod tests {
                     &format!("https://article{}.com", i),
                     None,
                     None,
+                    None,
                 )
                 .await
                 .unwrap();
             }
 
-            let items = db.get_items_for_feed(feed_id, 10, 0).await.unwrap();
+            let items = db.get_items_for_feed(feed_id, 10, None).await.unwrap();
             assert_eq!(items.len(), 5);
         }
 
@@ -497,15 +2074,15 @@ mod tests {
             let feeds = db.get_all_feeds().await.unwrap();
 
             // Same GUID in different feeds should create separate items
-            db.upsert_item(feeds[0].id, "guid-123", "Title 1", "https://a.com", None, None)
+            db.upsert_item(feeds[0].id, "guid-123", "Title 1", "https://a.com", None, None, None)
                 .await
                 .unwrap();
-            db.upsert_item(feeds[1].id, "guid-123", "Title 2", "https://b.com", None, None)
+            db.upsert_item(feeds[1].id, "guid-123", "Title 2", "https://b.com", None, None, None)
                 .await
                 .unwrap();
 
-            let items1 = db.get_items_for_feed(feeds[0].id, 10, 0).await.unwrap();
-            let items2 = db.get_items_for_feed(feeds[1].id, 10, 0).await.unwrap();
+            let items1 = db.get_items_for_feed(feeds[0].id, 10, None).await.unwrap();
+            let items2 = db.get_items_for_feed(feeds[1].id, 10, None).await.unwrap();
 
             assert_eq!(items1.len(), 1);
             assert_eq!(items2.len(), 1);
@@ -533,6 +2110,7 @@ mod tests {
                     &format!("Title {}", i),
                     &format!("https://article{}.com", i),
                     None,
+                    None,
                     Some(published),
                 )
                 .await
@@ -547,33 +2125,187 @@ mod tests {
             let db = create_test_db().await;
             let feed_id = setup_feed_with_items(&db, 20).await;
 
-            let items = db.get_items_for_feed(feed_id, 5, 0).await.unwrap();
+            let items = db.get_items_for_feed(feed_id, 5, None).await.unwrap();
             assert_eq!(items.len(), 5);
         }
 
         #[tokio::test]
-        async fn test_get_items_with_offset() {
+        async fn test_get_items_with_cursor() {
             let db = create_test_db().await;
             let feed_id = setup_feed_with_items(&db, 20).await;
 
-            let first_page = db.get_items_for_feed(feed_id, 5, 0).await.unwrap();
-            let second_page = db.get_items_for_feed(feed_id, 5, 5).await.unwrap();
+            let first_page = db.get_items_for_feed(feed_id, 5, None).await.unwrap();
+            let cursor = ItemCursor::from_item(first_page.last().unwrap());
+            let second_page = db
+                .get_items_for_feed(feed_id, 5, Some(&cursor))
+                .await
+                .unwrap();
 
             // Pages should have different items
             assert_eq!(first_page.len(), 5);
             assert_eq!(second_page.len(), 5);
-            assert_ne!(first_page[0].id, second_page[0].id);
+            assert_eq!(first_page[4].title, "Title 16");
+            assert_eq!(second_page[0].title, "Title 15");
+        }
+
+        #[tokio::test]
+        async fn test_get_recent_items_leaves_out_old_items() {
+            let db = create_test_db().await;
+            // Published 19 hours ago (Title 1) up to now (Title 20)
+            let feed_id = setup_feed_with_items(&db, 20).await;
+            db.upsert_item(feed_id, "undated", "Undated", "https://undated.com", None, None, None)
+                .await
+                .unwrap();
+            let since = Utc::now() - chrono::Duration::minutes(4 * 60 + 30);
+
+            let first_page = db
+                .get_recent_items_for_feed(feed_id, 3, None, Some(since))
+                .await
+                .unwrap();
+            let cursor = ItemCursor::from_item(first_page.last().unwrap());
+            let rest = db
+                .get_recent_items_for_feed(feed_id, 10, Some(&cursor), Some(since))
+                .await
+                .unwrap();
+
+            let titles: Vec<&str> = rest.iter().map(|i| i.title.as_str()).collect();
+            assert_eq!(first_page[0].title, "Title 20");
+            assert_eq!(titles, vec!["Title 17", "Title 16", "Undated"]);
+        }
+
+        #[tokio::test]
+        async fn test_get_recent_items_across_feeds() {
+            let db = create_test_db().await;
+            let first = setup_feed_with_items(&db, 3).await;
+            db.sync_feeds(&[
+                create_feed_config("Test", "https://test.com/rss", false),
+                create_feed_config("Other", "https://other.com/rss", false),
+            ])
+            .await
+            .unwrap();
+            let second = db.get_all_feeds().await.unwrap()[1].id;
+            let half_hour_ago = Utc::now() - chrono::Duration::minutes(30);
+            let o1 = "https://o1.com";
+            db.upsert_item(second, "o1", "Other 1", o1, None, None, Some(half_hour_ago))
+                .await
+                .unwrap();
+            db.upsert_item(second, "o2", "Other 2", "https://o2.com", None, None, None)
+                .await
+                .unwrap();
+
+            let first_page = db.get_recent_items(None, 2, None, None).await.unwrap();
+            let cursor = ItemCursor::from_item(first_page.last().unwrap());
+            let rest = db.get_recent_items(None, 10, Some(&cursor), None).await.unwrap();
+            let titles: Vec<&str> =
+                first_page.iter().chain(&rest).map(|i| i.title.as_str()).collect();
+            assert_eq!(titles, ["Title 3", "Other 1", "Title 2", "Title 1", "Other 2"]);
+            assert_eq!(first_page[0].feed_id, first);
+
+            // Undated items can't be placed after `since`
+            let since = Utc::now() - chrono::Duration::minutes(90);
+            let recent = db.get_recent_items(None, 10, None, Some(since)).await.unwrap();
+            let titles: Vec<&str> = recent.iter().map(|i| i.title.as_str()).collect();
+            assert_eq!(titles, ["Title 3", "Other 1", "Title 2"]);
+
+            let recent = db.get_recent_items(Some(second), 10, None, Some(since)).await.unwrap();
+            let titles: Vec<&str> = recent.iter().map(|i| i.title.as_str()).collect();
+            assert_eq!(titles, ["Other 1"]);
         }
 
         #[tokio::test]
-        async fn test_get_items_offset_beyond_count() {
+        async fn test_get_items_cursor_at_end() {
             let db = create_test_db().await;
             let feed_id = setup_feed_with_items(&db, 10).await;
 
-            let items = db.get_items_for_feed(feed_id, 10, 100).await.unwrap();
+            let all = db.get_items_for_feed(feed_id, 10, None).await.unwrap();
+            let cursor = ItemCursor::from_item(all.last().unwrap());
+            let items = db
+                .get_items_for_feed(feed_id, 10, Some(&cursor))
+                .await
+                .unwrap();
             assert!(items.is_empty());
         }
 
+        #[tokio::test]
+        async fn test_cursor_stable_when_new_items_arrive() {
+            let db = create_test_db().await;
+            let feed_id = setup_feed_with_items(&db, 10).await;
+
+            let first_page = db.get_items_for_feed(feed_id, 5, None).await.unwrap();
+            let cursor = ItemCursor::from_item(first_page.last().unwrap());
+
+            // A refresh inserts newer items between page loads
+            for i in 11..=13 {
+                db.upsert_item(
+                    feed_id,
+                    &format!("guid-{}", i),
+                    &format!("Title {}", i),
+                    &format!("https://article{}.com", i),
+                    None,
+                    None,
+                    Some(Utc::now() + chrono::Duration::hours(i)),
+                )
+                .await
+                .unwrap();
+            }
+
+            let second_page = db
+                .get_items_for_feed(feed_id, 5, Some(&cursor))
+                .await
+                .unwrap();
+            let titles: Vec<_> = second_page.iter().map(|i| i.title.as_str()).collect();
+            assert_eq!(titles, ["Title 5", "Title 4", "Title 3", "Title 2", "Title 1"]);
+        }
+
+        #[tokio::test]
+        async fn test_cursor_continues_into_undated_items() {
+            let db = create_test_db().await;
+            let feed_id = setup_feed_with_items(&db, 2).await;
+            for i in 3..=5 {
+                db.upsert_item(
+                    feed_id,
+                    &format!("guid-{}", i),
+                    &format!("Undated {}", i),
+                    &format!("https://article{}.com", i),
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            }
+
+            let first_page = db.get_items_for_feed(feed_id, 3, None).await.unwrap();
+            assert_eq!(first_page[2].title, "Undated 5");
+
+            let cursor = ItemCursor::from_item(first_page.last().unwrap());
+            let second_page = db
+                .get_items_for_feed(feed_id, 3, Some(&cursor))
+                .await
+                .unwrap();
+            let titles: Vec<_> = second_page.iter().map(|i| i.title.as_str()).collect();
+            assert_eq!(titles, ["Undated 4", "Undated 3"]);
+        }
+
+        #[test]
+        fn test_cursor_round_trip() {
+            let cursor = ItemCursor {
+                published: Some("2024-12-09T12:00:00+00:00".to_string()),
+                id: 42,
+            };
+            let parsed: ItemCursor = cursor.to_string().parse().unwrap();
+            assert_eq!(parsed, cursor);
+
+            let undated = ItemCursor {
+                published: None,
+                id: 7,
+            };
+            assert_eq!(undated.to_string(), "7");
+            assert_eq!("7".parse::<ItemCursor>().unwrap(), undated);
+
+            assert!("not-a-cursor".parse::<ItemCursor>().is_err());
+        }
+
         #[tokio::test]
         async fn test_get_item_count() {
             let db = create_test_db().await;
@@ -594,12 +2326,28 @@ mod tests {
             assert_eq!(count, 0);
         }
 
+        #[tokio::test]
+        async fn test_get_item_counts() {
+            let db = create_test_db().await;
+            let feed_id = setup_feed_with_items(&db, 15).await;
+            db.sync_feeds(&[
+                create_feed_config("Test", "https://test.com/rss", false),
+                create_feed_config("Empty", "https://empty.com/rss", false),
+            ])
+            .await
+            .unwrap();
+
+            let counts = db.get_item_counts().await.unwrap();
+            assert_eq!(counts.len(), 1);
+            assert_eq!(counts[&feed_id], 15);
+        }
+
         #[tokio::test]
         async fn test_items_ordered_by_published_desc() {
             let db = create_test_db().await;
             let feed_id = setup_feed_with_items(&db, 5).await;
 
-            let items = db.get_items_for_feed(feed_id, 10, 0).await.unwrap();
+            let items = db.get_items_for_feed(feed_id, 10, None).await.unwrap();
 
             // Most recent should be first (Title 5 has the most recent timestamp)
             assert_eq!(items[0].title, "Title 5");
@@ -607,6 +2355,174 @@ mod tests {
         }
     }
 
+    // Fetch log tests
+    mod fetch_log_tests {
+        use super::*;
+
+        fn create_record(feed_id: i64, started_at: DateTime<Utc>) -> FetchRecord {
+            FetchRecord {
+                feed_id,
+                started_at: started_at.to_rfc3339(),
+                duration_ms: 120,
+                http_status: Some(200),
+                bytes: Some(2048),
+                new_items: 3,
+                updated_items: 1,
+                filtered_items: 0,
+                error_category: None,
+            }
+        }
+
+        #[tokio::test]
+        async fn test_record_and_read_fetch_log() {
+            let db = create_test_db().await;
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", false)];
+            db.sync_feeds(&configs).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+
+            db.record_fetch(&create_record(feed_id, Utc::now()), &[])
+                .await
+                .unwrap();
+            db.record_fetch(
+                &FetchRecord {
+                    http_status: Some(503),
+                    error_category: Some("http".to_string()),
+                    ..create_record(feed_id, Utc::now())
+                },
+                &[],
+            )
+            .await
+            .unwrap();
+
+            let since = Utc::now() - chrono::Duration::days(1);
+            let log = db.get_fetch_log(feed_id, since).await.unwrap();
+            assert_eq!(log.len(), 2);
+            assert_eq!(log[0].new_items, 3);
+            assert!(log[0].error_category.is_none());
+            assert_eq!(log[1].error_category.as_deref(), Some("http"));
+        }
+
+        #[tokio::test]
+        async fn test_prune_fetch_log() {
+            let db = create_test_db().await;
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", false)];
+            db.sync_feeds(&configs).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+
+            let old = Utc::now() - chrono::Duration::days(FETCH_LOG_RETENTION_DAYS + 1);
+            db.record_fetch(&create_record(feed_id, old), &[]).await.unwrap();
+            db.record_fetch(&create_record(feed_id, Utc::now()), &[])
+                .await
+                .unwrap();
+
+            let pruned = db.prune_fetch_log().await.unwrap();
+            assert_eq!(pruned, 1);
+
+            let since = Utc::now() - chrono::Duration::days(365);
+            assert_eq!(db.get_fetch_log(feed_id, since).await.unwrap().len(), 1);
+        }
+
+        #[tokio::test]
+        async fn test_filter_counts_are_summed_per_rule() {
+            let db = create_test_db().await;
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", false)];
+            db.sync_feeds(&configs).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+
+            let sponsor = "exclude title \"sponsor\"".to_string();
+            let deals = "exclude category /deals/".to_string();
+            db.record_fetch(
+                &create_record(feed_id, Utc::now()),
+                &[(sponsor.clone(), 1), (deals.clone(), 3)],
+            )
+            .await
+            .unwrap();
+            db.record_fetch(&create_record(feed_id, Utc::now()), &[(sponsor.clone(), 4)])
+                .await
+                .unwrap();
+
+            let since = Utc::now() - chrono::Duration::days(1);
+            let counts = db.get_filter_counts(feed_id, since).await.unwrap();
+            assert_eq!(counts, vec![(sponsor, 5), (deals, 3)]);
+
+            // Counts go with their fetch log entries when those are pruned
+            sqlx::query("DELETE FROM fetch_log").execute(&db.pool).await.unwrap();
+            assert!(db.get_filter_counts(feed_id, since).await.unwrap().is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_item_publication_queries() {
+            let db = create_test_db().await;
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", false)];
+            db.sync_feeds(&configs).await.unwrap();
+            let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+
+            assert!(db.get_latest_published(feed_id).await.unwrap().is_none());
+
+            let newest = Utc::now() - chrono::Duration::days(1);
+            for (i, age) in [1, 5, 40].iter().enumerate() {
+                db.upsert_item(
+                    feed_id,
+                    &format!("guid-{}", i),
+                    "Title",
+                    "https://a.com",
+                    None,
+                    None,
+                    Some(Utc::now() - chrono::Duration::days(*age)),
+                )
+                .await
+                .unwrap();
+            }
+
+            let since = Utc::now() - chrono::Duration::days(30);
+            assert_eq!(db.count_items_published_since(feed_id, since).await.unwrap(), 2);
+
+            let latest = db.get_latest_published(feed_id).await.unwrap().unwrap();
+            assert!(latest.starts_with(&newest.format("%Y-%m-%d").to_string()));
+        }
+    }
+
+    // Maintenance tests
+    mod maintenance_tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_backup_to_file() {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let url = format!("sqlite:{}?mode=rwc", temp_dir.path().join("live.db").display());
+            let db = Database::new(&url).await.unwrap();
+            db.initialize().await.unwrap();
+            let configs = vec![create_feed_config("Test", "https://test.com/rss", false)];
+            db.sync_feeds(&configs).await.unwrap();
+
+            let backup_path = temp_dir.path().join("backup.db");
+            db.backup_to(&backup_path).await.unwrap();
+
+            let url = format!("sqlite:{}", backup_path.display());
+            let backup = Database::new(&url).await.unwrap();
+            let feeds = backup.get_all_feeds().await.unwrap();
+            assert_eq!(feeds.len(), 1);
+            assert_eq!(feeds[0].name, "Test");
+        }
+
+        #[tokio::test]
+        async fn test_backup_refuses_existing_file() {
+            let temp_file = tempfile::NamedTempFile::new().unwrap();
+            let db = create_test_db().await;
+
+            let result = db.backup_to(temp_file.path()).await;
+            assert!(result.is_err());
+        }
+
+        #[tokio::test]
+        async fn test_integrity_check_healthy() {
+            let db = create_test_db().await;
+
+            let problems = db.integrity_check().await.unwrap();
+            assert!(problems.is_empty());
+        }
+    }
+
     // Update feed fetched tests
     mod update_feed_fetched_tests {
         use super::*;
@@ -0,0 +1,373 @@
+//! Editing the `[[feeds]]` entries of feeds.toml and the files it includes.
+//!
+//! Edits go through `toml_edit`, so comments, formatting and any settings the
+//! admin page doesn't show are left as they were. Values are read and written
+//! as they appear in the file, before `${VAR}` interpolation.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, Item, Table, TableLike};
+
+use crate::config::Config;
+
+/// Reported when a form refers to a feed that has since moved or gone
+pub const STALE_PAGE: &str =
+    "the feed list changed since the page was loaded; reload it and try again";
+
+/// A feed as written in its file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedEntry {
+    /// Position among the file's feeds
+    pub index: usize,
+    pub name: String,
+    pub url: String,
+    pub group: Option<String>,
+    pub has_discussion: bool,
+    pub disabled: bool,
+}
+
+/// A file that defines feeds: the config file, or one it includes
+#[derive(Debug)]
+pub struct FeedFile {
+    pub path: PathBuf,
+    pub feeds: Vec<FeedEntry>,
+    /// Why the file's feeds can't be edited here, if they can't
+    pub read_only: Option<String>,
+}
+
+/// The feed settings the admin page edits
+#[derive(Debug, Clone, Default)]
+pub struct FeedFields {
+    pub name: String,
+    pub url: String,
+    pub group: Option<String>,
+    pub has_discussion: bool,
+}
+
+/// A change to an existing feed
+#[derive(Debug)]
+pub enum FeedEdit {
+    Update(FeedFields),
+    MoveUp,
+    MoveDown,
+    SetDisabled(bool),
+    Delete,
+}
+
+/// The config file and the files it includes, with the feeds each defines
+pub fn feed_files(config_path: &Path, config: &Config) -> anyhow::Result<Vec<FeedFile>> {
+    let mut paths = vec![config_path.to_path_buf()];
+    paths.extend(config.include_files(config_path)?);
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let doc = read_document(&path)?;
+            let (feeds, read_only) = match doc.get("feeds") {
+                None => (Vec::new(), None),
+                Some(Item::ArrayOfTables(tables)) => (
+                    tables.iter().enumerate().map(|(i, t)| entry(i, t)).collect(),
+                    None,
+                ),
+                Some(Item::Value(toml_edit::Value::Array(array))) => {
+                    let feeds: Vec<FeedEntry> = array
+                        .iter()
+                        .filter_map(|v| v.as_inline_table())
+                        .enumerate()
+                        .map(|(i, t)| entry(i, t))
+                        .collect();
+                    let read_only = (!feeds.is_empty())
+                        .then(|| "feeds are written as an inline array".to_string());
+                    (feeds, read_only)
+                }
+                Some(_) => (Vec::new(), Some("`feeds` is not a list".to_string())),
+            };
+            Ok(FeedFile {
+                path,
+                feeds,
+                read_only,
+            })
+        })
+        .collect()
+}
+
+fn read_document(path: &Path) -> anyhow::Result<DocumentMut> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    content
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn entry(index: usize, table: &dyn TableLike) -> FeedEntry {
+    let text = |key| table.get(key).and_then(|item| item.as_str()).map(str::to_string);
+    let flag = |key| table.get(key).and_then(|item| item.as_bool()).unwrap_or(false);
+    FeedEntry {
+        index,
+        name: text("name").unwrap_or_default(),
+        url: text("url").unwrap_or_default(),
+        group: text("group"),
+        has_discussion: flag("has_discussion"),
+        disabled: flag("disabled"),
+    }
+}
+
+/// Append a feed to the file's `[[feeds]]`
+pub fn add_feed(content: &str, fields: &FeedFields) -> anyhow::Result<String> {
+    let mut doc: DocumentMut = content.parse()?;
+
+    let empty_inline = doc
+        .get("feeds")
+        .and_then(|item| item.as_array())
+        .is_some_and(|array| array.is_empty());
+    if doc.get("feeds").is_none() || empty_inline {
+        doc.insert("feeds", Item::ArrayOfTables(ArrayOfTables::new()));
+    }
+    let feeds = doc
+        .get_mut("feeds")
+        .and_then(|item| item.as_array_of_tables_mut())
+        .ok_or_else(|| anyhow!("feeds in this file are written as an inline array"))?;
+
+    let mut table = Table::new();
+    table.decor_mut().set_prefix("\n");
+    set_fields(&mut table, fields);
+    feeds.push(table);
+    Ok(doc.to_string())
+}
+
+/// Change the feed at `index`, which must still have the URL `expected_url`
+/// so that an edit made from a stale page can't hit the wrong feed
+pub fn edit_feed(
+    content: &str,
+    index: usize,
+    expected_url: &str,
+    edit: &FeedEdit,
+) -> anyhow::Result<String> {
+    let mut doc: DocumentMut = content.parse()?;
+    let feeds = doc
+        .get_mut("feeds")
+        .and_then(|item| item.as_array_of_tables_mut())
+        .ok_or_else(|| anyhow!("feeds in this file are written as an inline array"))?;
+
+    let current = feeds.get(index).and_then(|t| t.get("url")).and_then(|i| i.as_str());
+    if current != Some(expected_url) {
+        bail!(STALE_PAGE);
+    }
+
+    match edit {
+        FeedEdit::Update(fields) => set_fields(feeds.get_mut(index).unwrap(), fields),
+        FeedEdit::MoveUp if index > 0 => swap(feeds, index - 1, index)?,
+        FeedEdit::MoveDown if index + 1 < feeds.len() => swap(feeds, index, index + 1)?,
+        // Already first or last
+        FeedEdit::MoveUp | FeedEdit::MoveDown => {}
+        FeedEdit::SetDisabled(disabled) => {
+            let table = feeds.get_mut(index).unwrap();
+            if *disabled {
+                table["disabled"] = value(true);
+            } else {
+                table.remove("disabled");
+            }
+        }
+        FeedEdit::Delete => {
+            feeds.remove(index);
+            // The config file must still have a `feeds` list
+            if feeds.is_empty() {
+                doc["feeds"] = value(Array::new());
+            }
+        }
+    }
+    Ok(doc.to_string())
+}
+
+fn set_fields(table: &mut Table, fields: &FeedFields) {
+    table["name"] = value(&fields.name);
+    table["url"] = value(&fields.url);
+    match &fields.group {
+        Some(group) => table["group"] = value(group),
+        None => {
+            table.remove("group");
+        }
+    }
+    if fields.has_discussion {
+        table["has_discussion"] = value(true);
+    } else {
+        table.remove("has_discussion");
+    }
+}
+
+/// Swap two neighbouring feeds, along with the comments above them
+fn swap(feeds: &mut ArrayOfTables, first: usize, second: usize) -> anyhow::Result<()> {
+    let mut a = feeds.get(first).unwrap().clone();
+    let mut b = feeds.get(second).unwrap().clone();
+
+    // Sub-tables such as `[[feeds.include]]` are written by their own position
+    // in the file and wouldn't follow their feed
+    let has_subtables =
+        |t: &Table| t.iter().any(|(_, item)| item.is_table() || item.is_array_of_tables());
+    if has_subtables(&a) || has_subtables(&b) {
+        bail!("feeds with sub-tables can't be reordered here; move them in the file");
+    }
+
+    // Tables are written in order of their position, not their index
+    if let (Some(pa), Some(pb)) = (a.position(), b.position()) {
+        a.set_position(pb);
+        b.set_position(pa);
+    }
+    *feeds.get_mut(first).unwrap() = b;
+    *feeds.get_mut(second).unwrap() = a;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = r#"refresh_interval = 15
+
+# Tech news
+[[feeds]]
+name = "One"
+url = "https://one.com/rss"
+group = "Tech"
+
+# Comments keep their feed
+[[feeds]]
+name = "Two"
+url = "https://two.com/rss"
+has_discussion = true
+max_age_days = 7
+
+[keywords]
+mute = [{ keyword = "crypto" }]
+"#;
+
+    fn fields(name: &str, url: &str) -> FeedFields {
+        FeedFields {
+            name: name.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn names(content: &str) -> Vec<String> {
+        Config::from_str(content)
+            .unwrap()
+            .feeds
+            .into_iter()
+            .map(|f| f.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_add_feed_appends_after_last_feed() {
+        let edited = add_feed(CONTENT, &fields("Three", "https://three.com/rss")).unwrap();
+
+        assert_eq!(names(&edited), vec!["One", "Two", "Three"]);
+        assert!(edited.contains("# Tech news"));
+        assert!(edited.ends_with("[keywords]\nmute = [{ keyword = \"crypto\" }]\n"));
+    }
+
+    #[test]
+    fn test_add_feed_to_empty_file() {
+        let edited = add_feed("feeds = []\n", &fields("New", "https://new.com/rss")).unwrap();
+
+        assert_eq!(edited, "\n[[feeds]]\nname = \"New\"\nurl = \"https://new.com/rss\"\n");
+    }
+
+    #[test]
+    fn test_update_keeps_other_settings() {
+        let update = FeedEdit::Update(FeedFields {
+            group: Some("News".to_string()),
+            ..fields("Deux", "https://two.com/feed")
+        });
+        let edited = edit_feed(CONTENT, 1, "https://two.com/rss", &update).unwrap();
+
+        let config = Config::from_str(&edited).unwrap();
+        let feed = &config.feeds[1];
+        assert_eq!(feed.name, "Deux");
+        assert_eq!(feed.url, "https://two.com/feed");
+        assert_eq!(feed.group.as_deref(), Some("News"));
+        assert!(!feed.has_discussion);
+        assert_eq!(feed.max_age_days, Some(7));
+        assert!(!edited.contains("has_discussion"));
+    }
+
+    #[test]
+    fn test_move_swaps_feeds_with_their_comments() {
+        let edited = edit_feed(CONTENT, 1, "https://two.com/rss", &FeedEdit::MoveUp).unwrap();
+
+        assert_eq!(names(&edited), vec!["Two", "One"]);
+        let two = edited.find("# Comments keep their feed").unwrap();
+        let one = edited.find("# Tech news").unwrap();
+        assert!(two < one);
+        assert!(edited.find("[keywords]").unwrap() > one);
+
+        // Moving the first feed up changes nothing
+        let unchanged = edit_feed(CONTENT, 0, "https://one.com/rss", &FeedEdit::MoveUp).unwrap();
+        assert_eq!(unchanged, CONTENT);
+    }
+
+    #[test]
+    fn test_disable_and_enable() {
+        let disable = FeedEdit::SetDisabled(true);
+        let edited = edit_feed(CONTENT, 0, "https://one.com/rss", &disable).unwrap();
+
+        let config = Config::from_str(&edited).unwrap();
+        assert_eq!(config.feeds.len(), 1);
+        assert_eq!(config.disabled_feeds[0].name, "One");
+
+        let enable = FeedEdit::SetDisabled(false);
+        let edited = edit_feed(&edited, 0, "https://one.com/rss", &enable).unwrap();
+        assert_eq!(edited, CONTENT);
+    }
+
+    #[test]
+    fn test_delete_last_feed_leaves_empty_list() {
+        let edited = edit_feed(CONTENT, 0, "https://one.com/rss", &FeedEdit::Delete).unwrap();
+        assert_eq!(names(&edited), vec!["Two"]);
+
+        let edited = edit_feed(&edited, 0, "https://two.com/rss", &FeedEdit::Delete).unwrap();
+        assert!(names(&edited).is_empty());
+
+        let edited = add_feed(&edited, &fields("Back", "https://back.com/rss")).unwrap();
+        assert_eq!(names(&edited), vec!["Back"]);
+    }
+
+    #[test]
+    fn test_stale_edit_is_refused() {
+        let err = edit_feed(CONTENT, 0, "https://two.com/rss", &FeedEdit::Delete).unwrap_err();
+        assert!(err.to_string().contains("changed since the page was loaded"));
+
+        let err = edit_feed(CONTENT, 5, "https://two.com/rss", &FeedEdit::Delete).unwrap_err();
+        assert!(err.to_string().contains("changed since the page was loaded"));
+    }
+
+    #[test]
+    fn test_feed_files_lists_raw_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("feeds.toml");
+        std::fs::write(
+            &main,
+            "include = [\"more.toml\"]\n\n[[feeds]]\nname = \"Env\"\n\
+             url = \"https://$${HOST}/rss\"\ndisabled = true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("more.toml"),
+            "feeds = [{ name = \"Inline\", url = \"https://inline.com/rss\" }]\n",
+        )
+        .unwrap();
+        let config = Config::load(&main).unwrap();
+
+        let files = feed_files(&main, &config).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, main);
+        assert_eq!(files[0].feeds[0].url, "https://$${HOST}/rss");
+        assert!(files[0].feeds[0].disabled);
+        assert!(files[0].read_only.is_none());
+        assert_eq!(files[1].feeds[0].name, "Inline");
+        assert!(files[1].read_only.is_some());
+    }
+}
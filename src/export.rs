@@ -0,0 +1,224 @@
+//! Versioned JSON snapshots of the database, used to move an instance
+//! between hosts or volumes.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+pub use crate::db::{FeedSnapshot, ItemSnapshot, RevisionSnapshot};
+use crate::db::Database;
+
+/// Format version written to and accepted from export documents
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportDocument {
+    pub version: u32,
+    pub exported_at: String,
+    pub app_version: String,
+    pub feeds: Vec<FeedSnapshot>,
+}
+
+/// Build an export document from the current database contents
+pub async fn export(db: &Database) -> anyhow::Result<ExportDocument> {
+    let enabled = db.get_all_feeds().await?.into_iter().map(|feed| (feed, false));
+    let disabled = db.get_disabled_feeds().await?.into_iter().map(|feed| (feed, true));
+
+    let mut feeds = Vec::new();
+    for (feed, disabled) in enabled.chain(disabled) {
+        let mut items = Vec::new();
+        for item in db.get_all_items_for_feed(feed.id).await? {
+            let revisions = if item.revision_count > 0 {
+                db.get_item_revisions(item.id)
+                    .await?
+                    .into_iter()
+                    .map(|revision| RevisionSnapshot {
+                        title: revision.title,
+                        link: revision.link,
+                        published: revision.published,
+                        revised_at: revision.revised_at,
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            items.push(ItemSnapshot {
+                guid: item.guid,
+                title: item.title,
+                link: item.link,
+                original_link: item.original_link,
+                discussion_link: item.discussion_link,
+                published: item.published,
+                is_read: item.is_read,
+                is_saved: item.is_saved,
+                revisions,
+            });
+        }
+
+        feeds.push(FeedSnapshot {
+            name: feed.name,
+            url: feed.url,
+            has_discussion: feed.has_discussion,
+            homepage_url: feed.homepage_url,
+            group_name: feed.group_name,
+            last_fetched: feed.last_fetched,
+            last_error: feed.last_error,
+            disabled,
+            items,
+        });
+    }
+
+    Ok(ExportDocument {
+        version: EXPORT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        feeds,
+    })
+}
+
+/// Load an export document into an initialized, empty database
+pub async fn import(db: &Database, doc: &ExportDocument) -> anyhow::Result<()> {
+    if doc.version != EXPORT_VERSION {
+        anyhow::bail!(
+            "Unsupported export version {} (expected {})",
+            doc.version,
+            EXPORT_VERSION
+        );
+    }
+
+    if !db.get_all_feeds().await?.is_empty() || !db.get_disabled_feeds().await?.is_empty() {
+        anyhow::bail!("Refusing to import into a database that already has feeds");
+    }
+
+    db.restore_feeds(&doc.feeds).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FeedConfig;
+
+    async fn create_test_db() -> Database {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.initialize().await.unwrap();
+        db
+    }
+
+    async fn create_populated_db() -> Database {
+        let db = create_test_db().await;
+        db.sync_feeds(&[FeedConfig {
+            name: "Test Feed".to_string(),
+            url: "https://test.com/rss".to_string(),
+            has_discussion: true,
+            ..Default::default()
+        }])
+        .await
+        .unwrap();
+
+        let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+        db.update_feed_fetched(feed_id, Some("Timeout"), Some("https://test.com"))
+            .await
+            .unwrap();
+        for i in 1..=3 {
+            db.upsert_item(
+                feed_id,
+                &format!("guid-{}", i),
+                &format!("Article {}", i),
+                &format!("https://test.com/{}", i),
+                None,
+                Some("https://test.com/comments"),
+                Some(Utc::now()),
+            )
+            .await
+            .unwrap();
+        }
+        db.upsert_item(
+            feed_id,
+            "guid-1",
+            "Article 1 (updated)",
+            "https://test.com/1",
+            None,
+            Some("https://test.com/comments"),
+            Some(Utc::now()),
+        )
+        .await
+        .unwrap();
+        let ids: Vec<i64> = db.get_items_for_feed(feed_id, 10, None).await.unwrap()[..2]
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        db.set_items_read(&ids, true).await.unwrap();
+        db.set_items_saved(&ids[..1], true).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_export_includes_feeds_and_items() {
+        let db = create_populated_db().await;
+
+        let doc = export(&db).await.unwrap();
+
+        assert_eq!(doc.version, EXPORT_VERSION);
+        assert_eq!(doc.feeds.len(), 1);
+        assert_eq!(doc.feeds[0].name, "Test Feed");
+        assert_eq!(doc.feeds[0].homepage_url.as_deref(), Some("https://test.com"));
+        assert_eq!(doc.feeds[0].last_error.as_deref(), Some("Timeout"));
+        assert_eq!(doc.feeds[0].items.len(), 3);
+        assert_eq!(doc.feeds[0].items.iter().filter(|i| i.is_read).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_through_json() {
+        let source = create_populated_db().await;
+        let json = serde_json::to_string(&export(&source).await.unwrap()).unwrap();
+
+        let target = create_test_db().await;
+        let doc: ExportDocument = serde_json::from_str(&json).unwrap();
+        import(&target, &doc).await.unwrap();
+
+        let feeds = target.get_all_feeds().await.unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].url, "https://test.com/rss");
+        assert!(feeds[0].has_discussion);
+        assert_eq!(feeds[0].last_error.as_deref(), Some("Timeout"));
+
+        let original = source.get_items_for_feed(1, 10, None).await.unwrap();
+        let restored = target.get_items_for_feed(feeds[0].id, 10, None).await.unwrap();
+        assert_eq!(restored.len(), 3);
+        for (a, b) in original.iter().zip(&restored) {
+            assert_eq!(a.guid, b.guid);
+            assert_eq!(a.published, b.published);
+            assert_eq!(a.discussion_link, b.discussion_link);
+            assert_eq!(a.revision_count, b.revision_count);
+            assert_eq!((a.is_read, a.is_saved), (b.is_read, b.is_saved));
+        }
+        assert_eq!(restored.iter().filter(|i| i.is_read).count(), 2);
+        assert_eq!(restored.iter().filter(|i| i.is_saved).count(), 1);
+
+        let edited = restored.iter().find(|i| i.guid == "guid-1").unwrap();
+        let revisions = target.get_item_revisions(edited.id).await.unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].title, "Article 1");
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_unknown_version() {
+        let db = create_test_db().await;
+        let doc = ExportDocument {
+            version: EXPORT_VERSION + 1,
+            exported_at: Utc::now().to_rfc3339(),
+            app_version: "0.0.0".to_string(),
+            feeds: vec![],
+        };
+
+        assert!(import(&db, &doc).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_non_empty_database() {
+        let db = create_populated_db().await;
+        let doc = export(&db).await.unwrap();
+
+        assert!(import(&db, &doc).await.is_err());
+    }
+}
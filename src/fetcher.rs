@@ -1,36 +1,225 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, Utc};
 use feed_rs::parser;
-use reqwest::Client;
-use tokio::sync::RwLock;
+use anyhow::Context;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy, RequestBuilder};
+use tokio::sync::{watch, RwLock};
 use tracing::{error, info, warn};
 
-use crate::db::{Database, Feed};
+use crate::config::{
+    Config, FeedAuth, FeedConfig, ProxyConfig, DEFAULT_TIMEOUT_SECS, DIRECT_PROXY,
+};
+use crate::db::{Database, Feed, FetchRecord, UpsertOutcome};
+use crate::filter::{EntryFields, FeedFilter};
+use crate::rewrite::{LinkCanonicalizer, TitleRewriter};
+
+/// Why a feed fetch failed
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("{0}")]
+    Network(#[from] reqwest::Error),
+    #[error("HTTP {0}")]
+    Http(reqwest::StatusCode),
+    #[error("{0}")]
+    Parse(#[from] parser::ParseFeedError),
+    #[error("{0}")]
+    Database(anyhow::Error),
+    #[error("{0}")]
+    Other(#[from] anyhow::Error),
+}
+
+impl FetchError {
+    /// Short category name stored in the fetch log
+    pub fn category(&self) -> &'static str {
+        match self {
+            FetchError::Network(_) => "network",
+            FetchError::Http(_) => "http",
+            FetchError::Parse(_) => "parse",
+            FetchError::Database(_) => "database",
+            FetchError::Other(_) => "other",
+        }
+    }
+}
+
+/// What was observed during one fetch, filled in as far as the fetch got
+#[derive(Debug, Default)]
+pub struct FetchAttempt {
+    pub http_status: Option<u16>,
+    pub bytes: Option<usize>,
+    pub new_items: i64,
+    pub updated_items: i64,
+    /// Entries dropped, keyed by the rule that dropped them
+    pub filtered: BTreeMap<String, i64>,
+    pub homepage_url: Option<String>,
+}
+
+/// Entries shown when previewing a feed
+pub const PREVIEW_ITEMS: usize = 5;
+
+/// What a feed URL serves, fetched and parsed without storing anything
+#[derive(Debug)]
+pub struct FeedPreview {
+    pub title: Option<String>,
+    pub homepage_url: Option<String>,
+    /// Total entries in the document
+    pub entry_count: usize,
+    /// Entries with a discussion link, as if the feed had `has_discussion` set
+    pub discussion_count: usize,
+    /// The first `PREVIEW_ITEMS` entries
+    pub items: Vec<PreviewItem>,
+}
+
+#[derive(Debug)]
+pub struct PreviewItem {
+    pub title: String,
+    pub link: String,
+    pub discussion_link: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+}
 
 pub struct Fetcher {
-    client: Client,
+    /// Client for feeds without a proxy of their own; rebuilt when `[proxy]` changes
+    client: RwLock<Client>,
     db: Arc<Database>,
     refreshing: Arc<RwLock<bool>>,
+    /// Include/exclude rules by feed URL, for feeds that have any
+    filters: RwLock<HashMap<String, Arc<FeedFilter>>>,
+    /// Per-feed request settings by feed URL, for feeds that set any
+    requests: RwLock<HashMap<String, Arc<RequestOptions>>>,
+    /// Title rules by feed URL, for feeds that have any
+    rewriters: RwLock<HashMap<String, Arc<TitleRewriter>>>,
+    /// Link clean-up for every feed
+    links: RwLock<Arc<LinkCanonicalizer>>,
+}
+
+/// Headers, credentials, timeout and proxy a feed asks to be fetched with
+#[derive(Debug, Default)]
+struct RequestOptions {
+    headers: HeaderMap,
+    auth: Option<FeedAuth>,
+    timeout: Option<Duration>,
+    /// Client going through the feed's own `proxy`, if it sets one
+    client: Option<Client>,
+}
+
+impl RequestOptions {
+    fn from_config(feed: &FeedConfig) -> anyhow::Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &feed.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        if let Some(agent) = &feed.user_agent {
+            headers.insert(USER_AGENT, HeaderValue::from_str(agent)?);
+        }
+
+        let client = match feed.proxy.as_deref() {
+            None => None,
+            Some(DIRECT_PROXY) => Some(client_builder().no_proxy().build()?),
+            Some(url) => Some(client_builder().proxy(Proxy::all(url)?).build()?),
+        };
+
+        Ok(Self {
+            headers,
+            auth: feed.auth.clone(),
+            timeout: feed.timeout_secs.map(Duration::from_secs),
+            client,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+            && self.auth.is_none()
+            && self.timeout.is_none()
+            && self.client.is_none()
+    }
+
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        request = request.headers(self.headers.clone());
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        match &self.auth {
+            Some(FeedAuth { bearer: Some(token), .. }) => request.bearer_auth(token),
+            Some(FeedAuth { username: Some(username), password, .. }) => {
+                request.basic_auth(username, password.as_ref())
+            }
+            _ => request,
+        }
+    }
+}
+
+/// Settings shared by every client the fetcher builds
+fn client_builder() -> ClientBuilder {
+    Client::builder()
+        .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+        .user_agent("MoarNews/1.0 (RSS Aggregator)")
+}
+
+/// Client for feeds that don't set their own `proxy`
+fn default_client(proxy: Option<&ProxyConfig>) -> anyhow::Result<Client> {
+    let builder = match proxy {
+        Some(proxy) => {
+            let no_proxy = NoProxy::from_string(&proxy.no_proxy.join(","));
+            client_builder().proxy(Proxy::all(&proxy.url)?.no_proxy(no_proxy))
+        }
+        None => client_builder(),
+    };
+    Ok(builder.build()?)
 }
 
 impl Fetcher {
     pub fn new(db: Arc<Database>) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("MoarNews/1.0 (RSS Aggregator)")
-            .build()
-            .expect("Failed to create HTTP client");
+        let client = default_client(None).expect("Failed to create HTTP client");
 
         Self {
-            client,
+            client: RwLock::new(client),
             db,
             refreshing: Arc::new(RwLock::new(false)),
+            filters: RwLock::new(HashMap::new()),
+            requests: RwLock::new(HashMap::new()),
+            rewriters: RwLock::new(HashMap::new()),
+            links: RwLock::new(Arc::default()),
         }
     }
 
+    /// Compile the include/exclude rules, title rules and request settings of every
+    /// configured feed, and set up the proxy and link clean-up, replacing whatever was
+    /// in force before
+    pub async fn configure_feeds(&self, config: &Config) -> anyhow::Result<()> {
+        let client = default_client(config.proxy.as_ref()).context("Invalid proxy settings")?;
+        let mut filters = HashMap::new();
+        let mut requests = HashMap::new();
+        let mut rewriters = HashMap::new();
+        for feed in &config.feeds {
+            let filter = FeedFilter::compile(feed)?;
+            if !filter.is_empty() {
+                filters.insert(feed.url.clone(), Arc::new(filter));
+            }
+            let rewriter = TitleRewriter::compile(feed)?;
+            if !rewriter.is_empty() {
+                rewriters.insert(feed.url.clone(), Arc::new(rewriter));
+            }
+            let options = RequestOptions::from_config(feed)
+                .with_context(|| format!("Invalid HTTP options for feed `{}`", feed.name))?;
+            if !options.is_empty() {
+                requests.insert(feed.url.clone(), Arc::new(options));
+            }
+        }
+        *self.client.write().await = client;
+        *self.filters.write().await = filters;
+        *self.requests.write().await = requests;
+        *self.rewriters.write().await = rewriters;
+        *self.links.write().await = Arc::new(LinkCanonicalizer::compile(&config.links));
+        Ok(())
+    }
+
     pub async fn is_refreshing(&self) -> bool {
         *self.refreshing.read().await
     }
@@ -62,11 +251,18 @@ impl Fetcher {
         info!("Refreshing {} feeds", feeds.len());
 
         for feed in feeds {
-            match self.refresh_feed(&feed).await {
-                Ok(((), homepage_url)) => {
+            let started_at = Utc::now();
+            let timer = Instant::now();
+            let mut attempt = FetchAttempt::default();
+
+            let result = self.refresh_feed(&feed, &mut attempt).await;
+            let duration_ms = timer.elapsed().as_millis() as i64;
+
+            match &result {
+                Ok(()) => {
                     let _ = self
                         .db
-                        .update_feed_fetched(feed.id, None, homepage_url.as_deref())
+                        .update_feed_fetched(feed.id, None, attempt.homepage_url.as_deref())
                         .await;
                 }
                 Err(e) => {
@@ -77,17 +273,124 @@ impl Fetcher {
                         .await;
                 }
             }
+
+            let record = FetchRecord {
+                feed_id: feed.id,
+                started_at: started_at.to_rfc3339(),
+                duration_ms,
+                http_status: attempt.http_status.map(i64::from),
+                bytes: attempt.bytes.map(|b| b as i64),
+                new_items: attempt.new_items,
+                updated_items: attempt.updated_items,
+                filtered_items: attempt.filtered.values().sum(),
+                error_category: result.err().map(|e| e.category().to_string()),
+            };
+            let filtered: Vec<(String, i64)> = attempt.filtered.into_iter().collect();
+            if let Err(e) = self.db.record_fetch(&record, &filtered).await {
+                warn!("Failed to record fetch for '{}': {}", feed.name, e);
+            }
+        }
+
+        if let Err(e) = self.db.prune_fetch_log().await {
+            warn!("Failed to prune fetch log: {}", e);
         }
 
         info!("Feed refresh complete");
         Ok(())
     }
 
-    async fn refresh_feed(&self, feed: &Feed) -> anyhow::Result<((), Option<String>)> {
-        info!("Fetching feed: {} ({})", feed.name, feed.url);
+    /// Fetch and parse `url` the way a refresh would, but store nothing.
+    /// Discussion links are looked for whether or not the feed will have them.
+    pub async fn preview(&self, url: &str) -> Result<FeedPreview, FetchError> {
+        let bytes = self.download(url, &mut FetchAttempt::default()).await?;
+        let comments_map = Self::extract_comments_from_xml(&bytes);
+        let parsed = parser::parse(&bytes[..])?;
+
+        let feed = Feed {
+            url: url.to_string(),
+            has_discussion: true,
+            ..Default::default()
+        };
+        let mut items = Vec::new();
+        let mut discussion_count = 0;
+        for entry in &parsed.entries {
+            let link = Self::entry_link(entry);
+            let discussion_link =
+                Self::extract_discussion_link(&feed, entry, comments_map.get(&link), &link);
+            if discussion_link.is_some() {
+                discussion_count += 1;
+            }
+            if items.len() < PREVIEW_ITEMS {
+                items.push(PreviewItem {
+                    title: Self::entry_title(entry),
+                    link,
+                    discussion_link,
+                    published: entry.published.or(entry.updated),
+                });
+            }
+        }
+
+        Ok(FeedPreview {
+            title: parsed.title.as_ref().map(|t| t.content.clone()),
+            homepage_url: Self::extract_homepage_url(&parsed, url),
+            entry_count: parsed.entries.len(),
+            discussion_count,
+            items,
+        })
+    }
+
+    /// Fetch the feed configured at `url` and pair each entry's title with what its
+    /// title rules make of it. Nothing is stored.
+    pub async fn preview_titles(
+        &self,
+        url: &str,
+        name: &str,
+    ) -> Result<Vec<(String, String)>, FetchError> {
+        let bytes = self.download(url, &mut FetchAttempt::default()).await?;
+        let parsed = parser::parse(&bytes[..])?;
+        let rewriter = self.rewriters.read().await.get(url).cloned();
+
+        Ok(parsed
+            .entries
+            .iter()
+            .map(|entry| {
+                let title = Self::entry_title(entry);
+                let rewritten = match &rewriter {
+                    Some(rewriter) => Self::rewrite_title(rewriter, &title, &parsed, name),
+                    None => title.clone(),
+                };
+                (title, rewritten)
+            })
+            .collect())
+    }
+
+    /// Fetch `url` with the request settings of the feed configured for it, if any
+    async fn download(&self, url: &str, attempt: &mut FetchAttempt) -> Result<Vec<u8>, FetchError> {
+        let options = self.requests.read().await.get(url).cloned();
+        let client = match options.as_ref().and_then(|options| options.client.clone()) {
+            Some(client) => client,
+            None => self.client.read().await.clone(),
+        };
+        let mut request = client.get(url);
+        if let Some(options) = &options {
+            request = options.apply(request);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        attempt.http_status = Some(status.as_u16());
+        if !status.is_success() {
+            return Err(FetchError::Http(status));
+        }
 
-        let response = self.client.get(&feed.url).send().await?;
         let bytes = response.bytes().await?;
+        attempt.bytes = Some(bytes.len());
+        Ok(bytes.to_vec())
+    }
+
+    async fn refresh_feed(&self, feed: &Feed, attempt: &mut FetchAttempt) -> Result<(), FetchError> {
+        info!("Fetching feed: {} ({})", feed.name, feed.url);
+
+        let bytes = self.download(&feed.url, attempt).await?;
 
         // Extract comments URLs from raw XML (feed_rs doesn't parse RSS <comments> element)
         let comments_map = Self::extract_comments_from_xml(&bytes);
@@ -95,56 +398,116 @@ impl Fetcher {
         let parsed = parser::parse(&bytes[..])?;
 
         // Extract homepage URL from feed metadata
-        let homepage_url = Self::extract_homepage_url(&parsed, &feed.url);
+        attempt.homepage_url = Self::extract_homepage_url(&parsed, &feed.url);
+
+        let filter = self.filters.read().await.get(&feed.url).cloned();
+        let rewriter = self.rewriters.read().await.get(&feed.url).cloned();
+        let links = self.links.read().await.clone();
 
         let mut count = 0;
-        for entry in parsed.entries {
-            let guid = entry.id.clone();
-
-            let title = entry
-                .title
-                .as_ref()
-                .map(|t| t.content.clone())
-                .unwrap_or_else(|| "Untitled".to_string());
-
-            // Get the main link - for HN/Lobste.rs, the actual article is typically the first link
-            let link = entry
-                .links
-                .first()
-                .map(|l| l.href.clone())
-                .unwrap_or_default();
-
-            if link.is_empty() {
+        for entry in &parsed.entries {
+            let mut title = Self::entry_title(entry);
+            if let Some(rewriter) = &rewriter {
+                title = Self::rewrite_title(rewriter, &title, &parsed, &feed.name);
+            }
+            let original_link = Self::entry_link(entry);
+
+            if original_link.is_empty() {
                 warn!("Skipping entry with no link: {}", title);
                 continue;
             }
 
+            let canonical = links.apply(&original_link);
+            let link = canonical.as_deref().unwrap_or(&original_link);
+
+            if let Some(filter) = &filter {
+                let fields = EntryFields {
+                    title: &title,
+                    link,
+                    authors: entry.authors.iter().map(|a| a.name.as_str()).collect(),
+                    categories: entry
+                        .categories
+                        .iter()
+                        .map(|c| c.label.as_deref().unwrap_or(&c.term))
+                        .collect(),
+                };
+                if let Some(rule) = filter.check(&fields) {
+                    *attempt.filtered.entry(rule.to_string()).or_default() += 1;
+                    continue;
+                }
+            }
+
             // Get discussion link for HN/Lobste.rs
-            let discussion_link =
-                Self::extract_discussion_link(feed, &entry, comments_map.get(&link), &link);
+            let discussion_link = Self::extract_discussion_link(
+                feed,
+                entry,
+                comments_map.get(&original_link),
+                &original_link,
+            );
 
             // Get published date
-            let published: Option<DateTime<Utc>> = entry
-                .published
-                .or(entry.updated)
-                .map(|dt| dt.into());
+            let published: Option<DateTime<Utc>> = entry.published.or(entry.updated);
 
-            self.db
+            let outcome = self
+                .db
                 .upsert_item(
                     feed.id,
-                    &guid,
+                    &entry.id,
                     &title,
-                    &link,
+                    link,
+                    canonical.is_some().then_some(original_link.as_str()),
                     discussion_link.as_deref(),
                     published,
                 )
-                .await?;
+                .await
+                .map_err(FetchError::Database)?;
 
+            match outcome {
+                UpsertOutcome::Inserted => attempt.new_items += 1,
+                UpsertOutcome::Updated => attempt.updated_items += 1,
+                UpsertOutcome::Unchanged => {}
+            }
             count += 1;
         }
 
-        info!("Added/updated {} items for feed '{}'", count, feed.name);
-        Ok(((), homepage_url))
+        info!(
+            "Processed {} items for feed '{}' ({} new, {} updated, {} filtered)",
+            count,
+            feed.name,
+            attempt.new_items,
+            attempt.updated_items,
+            attempt.filtered.values().sum::<i64>()
+        );
+        Ok(())
+    }
+
+    /// Apply title rules; the site names a suffix may give are the feed's own title
+    /// and its configured name
+    fn rewrite_title(
+        rewriter: &TitleRewriter,
+        title: &str,
+        parsed: &feed_rs::model::Feed,
+        name: &str,
+    ) -> String {
+        let site = parsed.title.as_ref().map(|t| t.content.as_str()).unwrap_or_default();
+        rewriter.apply(title, &[site, name])
+    }
+
+    fn entry_title(entry: &feed_rs::model::Entry) -> String {
+        entry
+            .title
+            .as_ref()
+            .map(|t| t.content.clone())
+            .unwrap_or_else(|| "Untitled".to_string())
+    }
+
+    /// The main link - for HN/Lobste.rs, the actual article is typically the first link
+    fn entry_link(entry: &feed_rs::model::Entry) -> String {
+        entry
+            .links
+            .first()
+            .map(|l| l.href.clone())
+            .unwrap_or_default()
     }
 
     /// Extract the homepage URL from feed metadata.
@@ -243,10 +606,8 @@ impl Fetcher {
         }
 
         // For Lobste.rs, the guid/id is the discussion URL
-        if feed.url.contains("lobste.rs") {
-            if entry.id.contains("lobste.rs/s/") {
-                return Some(entry.id.clone());
-            }
+        if feed.url.contains("lobste.rs") && entry.id.contains("lobste.rs/s/") {
+            return Some(entry.id.clone());
         }
 
         // For other feeds: check if we extracted a <comments> URL from raw XML
@@ -266,9 +627,9 @@ impl Fetcher {
     }
 }
 
-pub async fn start_background_refresh(fetcher: Arc<Fetcher>, interval_minutes: u64) {
-    let interval = Duration::from_secs(interval_minutes * 60);
-
+/// Refresh all feeds now and then every `interval` minutes.
+/// A new interval takes effect as soon as it is published, restarting the wait.
+pub async fn start_background_refresh(fetcher: Arc<Fetcher>, mut interval: watch::Receiver<u64>) {
     // Do initial fetch
     info!("Starting initial feed fetch");
     if let Err(e) = fetcher.refresh_all_feeds().await {
@@ -277,10 +638,18 @@ pub async fn start_background_refresh(fetcher: Arc<Fetcher>, interval_minutes: u
 
     // Then schedule periodic refreshes
     loop {
-        tokio::time::sleep(interval).await;
-        info!("Starting scheduled feed refresh");
-        if let Err(e) = fetcher.refresh_all_feeds().await {
-            error!("Scheduled feed refresh failed: {}", e);
+        let minutes = *interval.borrow_and_update();
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(minutes * 60)) => {
+                info!("Starting scheduled feed refresh");
+                if let Err(e) = fetcher.refresh_all_feeds().await {
+                    error!("Scheduled feed refresh failed: {}", e);
+                }
+            }
+            // Disabled once the sender is gone, leaving the last interval in force
+            Ok(()) = interval.changed() => {
+                info!("Refresh interval changed to {} minutes", *interval.borrow());
+            }
         }
     }
 }
@@ -299,6 +668,7 @@ mod tests {
             last_fetched: None,
             last_error: None,
             homepage_url: None,
+            ..Default::default()
         }
     }
 
@@ -679,6 +1049,535 @@ mod tests {
         }
     }
 
+    // Tests for refreshing against a mock server
+    mod refresh_tests {
+        use super::*;
+        use crate::config::FeedConfig;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::{TcpListener, TcpStream};
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <rss version="2.0">
+                <channel>
+                    <title>Mock</title>
+                    <link>https://mock.example.com</link>
+                    <item>
+                        <title>First</title>
+                        <link>https://mock.example.com/1</link>
+                        <guid>1</guid>
+                    </item>
+                    <item>
+                        <title>Second</title>
+                        <link>https://mock.example.com/2</link>
+                        <guid>2</guid>
+                    </item>
+                </channel>
+            </rss>"#;
+
+        async fn create_fetcher(url: String) -> (Fetcher, Arc<Database>) {
+            let db = Database::new("sqlite::memory:").await.unwrap();
+            db.initialize().await.unwrap();
+            db.sync_feeds(&[FeedConfig {
+                name: "Mock".to_string(),
+                url,
+                has_discussion: false,
+                ..Default::default()
+            }])
+            .await
+            .unwrap();
+            let db = Arc::new(db);
+            (Fetcher::new(db.clone()), db)
+        }
+
+        fn since() -> DateTime<Utc> {
+            Utc::now() - chrono::Duration::hours(1)
+        }
+
+        #[tokio::test]
+        async fn test_successful_fetch_is_logged() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/rss"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(RSS))
+                .mount(&server)
+                .await;
+
+            let (fetcher, db) = create_fetcher(format!("{}/rss", server.uri())).await;
+            fetcher.refresh_all_feeds().await.unwrap();
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            let log = db.get_fetch_log(1, since()).await.unwrap();
+            assert_eq!(log.len(), 2);
+            assert_eq!(log[0].http_status, Some(200));
+            assert_eq!(log[0].bytes, Some(RSS.len() as i64));
+            assert_eq!(log[0].new_items, 2);
+            assert!(log[0].error_category.is_none());
+            // Nothing changed upstream on the second pass
+            assert_eq!(log[1].new_items, 0);
+            assert_eq!(log[1].updated_items, 0);
+        }
+
+        #[tokio::test]
+        async fn test_filtered_entries_are_counted_not_stored() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(RSS))
+                .mount(&server)
+                .await;
+
+            let url = format!("{}/rss", server.uri());
+            let (fetcher, db) = create_fetcher(url.clone()).await;
+            let config = Config::from_str(&format!(
+                r#"
+                [[feeds]]
+                name = "Mock"
+                url = "{}"
+                exclude = [{{ field = "title", keyword = "second" }}]
+                "#,
+                url
+            ))
+            .unwrap();
+            fetcher.configure_feeds(&config).await.unwrap();
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            let items = db.get_all_items_for_feed(1).await.unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].title, "First");
+
+            let log = db.get_fetch_log(1, since()).await.unwrap();
+            assert_eq!(log[0].new_items, 1);
+            assert_eq!(log[0].filtered_items, 1);
+            assert_eq!(
+                db.get_filter_counts(1, since()).await.unwrap(),
+                vec![("exclude title \"second\"".to_string(), 1)]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_feed_request_options_are_sent() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(header("x-api-key", "abc"))
+                .and(header("authorization", "Bearer s3cret"))
+                .and(header("user-agent", "Mozilla/5.0"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(RSS))
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(401))
+                .mount(&server)
+                .await;
+
+            let url = format!("{}/rss", server.uri());
+            let (fetcher, db) = create_fetcher(url.clone()).await;
+            fetcher.refresh_all_feeds().await.unwrap();
+            assert_eq!(db.get_fetch_log(1, since()).await.unwrap()[0].http_status, Some(401));
+
+            let config = Config::from_str(&format!(
+                r#"
+                [[feeds]]
+                name = "Mock"
+                url = "{}"
+                headers = {{ "X-Api-Key" = "abc" }}
+                auth = {{ bearer = "s3cret" }}
+                user_agent = "Mozilla/5.0"
+                "#,
+                url
+            ))
+            .unwrap();
+            fetcher.configure_feeds(&config).await.unwrap();
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            assert_eq!(db.get_fetch_log(1, since()).await.unwrap()[1].http_status, Some(200));
+            assert_eq!(db.get_all_items_for_feed(1).await.unwrap().len(), 2);
+        }
+
+        #[tokio::test]
+        async fn test_feed_timeout_and_basic_auth() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(header("authorization", "Basic bWU6cHc="))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_string(RSS)
+                        .set_delay(Duration::from_secs(2)),
+                )
+                .mount(&server)
+                .await;
+
+            let url = format!("{}/rss", server.uri());
+            let (fetcher, db) = create_fetcher(url.clone()).await;
+            let config = Config::from_str(&format!(
+                r#"
+                [[feeds]]
+                name = "Mock"
+                url = "{}"
+                auth = {{ username = "me", password = "pw" }}
+                timeout_secs = 1
+                "#,
+                url
+            ))
+            .unwrap();
+            fetcher.configure_feeds(&config).await.unwrap();
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            // The default timeout would have waited out the delay
+            let log = db.get_fetch_log(1, since()).await.unwrap();
+            assert_eq!(log[0].http_status, None);
+            assert_eq!(log[0].error_category.as_deref(), Some("network"));
+        }
+
+        /// Minimal SOCKS5 proxy: no authentication, CONNECT only. Counts the connections
+        /// it relays.
+        async fn start_socks5_proxy() -> (String, Arc<AtomicUsize>) {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let relayed = Arc::new(AtomicUsize::new(0));
+            let counter = relayed.clone();
+            tokio::spawn(async move {
+                while let Ok((mut client, _)) = listener.accept().await {
+                    let counter = counter.clone();
+                    tokio::spawn(async move {
+                        let mut greeting = [0u8; 2];
+                        client.read_exact(&mut greeting).await?;
+                        let mut methods = vec![0u8; greeting[1] as usize];
+                        client.read_exact(&mut methods).await?;
+                        client.write_all(&[5, 0]).await?;
+
+                        let mut request = [0u8; 4];
+                        client.read_exact(&mut request).await?;
+                        let host = match request[3] {
+                            1 => {
+                                let mut ip = [0u8; 4];
+                                client.read_exact(&mut ip).await?;
+                                std::net::Ipv4Addr::from(ip).to_string()
+                            }
+                            _ => {
+                                let mut len = [0u8; 1];
+                                client.read_exact(&mut len).await?;
+                                let mut name = vec![0u8; len[0] as usize];
+                                client.read_exact(&mut name).await?;
+                                String::from_utf8_lossy(&name).into_owned()
+                            }
+                        };
+                        let mut port = [0u8; 2];
+                        client.read_exact(&mut port).await?;
+
+                        let mut upstream =
+                            TcpStream::connect((host.as_str(), u16::from_be_bytes(port))).await?;
+                        client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+                        Ok::<_, std::io::Error>(())
+                    });
+                }
+            });
+            (format!("socks5://{}", address), relayed)
+        }
+
+        #[tokio::test]
+        async fn test_fetch_through_http_proxy() {
+            let proxy = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/rss"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(RSS))
+                .mount(&proxy)
+                .await;
+
+            // Only reachable through the proxy
+            let url = "http://feeds.example.invalid/rss".to_string();
+            let (fetcher, db) = create_fetcher(url.clone()).await;
+            let config = Config::from_str(&format!(
+                r#"
+                [proxy]
+                url = "{}"
+
+                [[feeds]]
+                name = "Mock"
+                url = "{}"
+                "#,
+                proxy.uri(),
+                url
+            ))
+            .unwrap();
+            fetcher.configure_feeds(&config).await.unwrap();
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            assert_eq!(proxy.received_requests().await.unwrap().len(), 1);
+            assert_eq!(db.get_all_items_for_feed(1).await.unwrap().len(), 2);
+        }
+
+        #[tokio::test]
+        async fn test_fetch_through_socks5_proxy() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(RSS))
+                .mount(&server)
+                .await;
+            let (proxy, relayed) = start_socks5_proxy().await;
+
+            let url = format!("{}/rss", server.uri());
+            let (fetcher, db) = create_fetcher(url.clone()).await;
+            let config = Config::from_str(&format!(
+                r#"
+                [[feeds]]
+                name = "Mock"
+                url = "{}"
+                proxy = "{}"
+                "#,
+                url, proxy
+            ))
+            .unwrap();
+            fetcher.configure_feeds(&config).await.unwrap();
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            assert_eq!(relayed.load(Ordering::SeqCst), 1);
+            assert_eq!(db.get_all_items_for_feed(1).await.unwrap().len(), 2);
+        }
+
+        #[tokio::test]
+        async fn test_no_proxy_and_direct_feeds_skip_proxy() {
+            let proxy = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(502))
+                .mount(&proxy)
+                .await;
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(RSS))
+                .mount(&server)
+                .await;
+
+            let url = format!("{}/rss", server.uri());
+            let (fetcher, db) = create_fetcher(url.clone()).await;
+            let fetch_status = |extra: &str, feed_extra: &str| {
+                let config = Config::from_str(&format!(
+                    "[proxy]\nurl = \"{}\"\n{}\n\n[[feeds]]\nname = \"Mock\"\nurl = \"{}\"\n{}",
+                    proxy.uri(),
+                    extra,
+                    url,
+                    feed_extra
+                ))
+                .unwrap();
+                let fetcher = &fetcher;
+                let db = &db;
+                async move {
+                    fetcher.configure_feeds(&config).await.unwrap();
+                    fetcher.refresh_all_feeds().await.unwrap();
+                    db.get_fetch_log(1, since()).await.unwrap().last().unwrap().http_status
+                }
+            };
+
+            assert_eq!(fetch_status("", "").await, Some(502));
+            assert_eq!(fetch_status("no_proxy = [\"127.0.0.1\"]", "").await, Some(200));
+            assert_eq!(fetch_status("", "proxy = \"direct\"").await, Some(200));
+            assert_eq!(proxy.received_requests().await.unwrap().len(), 1);
+        }
+
+        #[tokio::test]
+        async fn test_titles_are_rewritten_before_filtering_and_storing() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(
+                    RSS.replace("<title>First</title>", "<title>★ First\n  story | Mock</title>"),
+                ))
+                .mount(&server)
+                .await;
+
+            let url = format!("{}/rss", server.uri());
+            let (fetcher, db) = create_fetcher(url.clone()).await;
+            let config = Config::from_str(&format!(
+                r#"
+                [[feeds]]
+                name = "Mock"
+                url = "{}"
+                collapse_whitespace = true
+                strip_site_suffix = true
+                title_rewrites = [{{ regex = "^★ " }}]
+                exclude = [{{ field = "title", regex = "^Second$" }}]
+                "#,
+                url
+            ))
+            .unwrap();
+            fetcher.configure_feeds(&config).await.unwrap();
+
+            let titles = fetcher.preview_titles(&url, "Mock").await.unwrap();
+            assert_eq!(
+                titles,
+                vec![
+                    ("★ First\n  story | Mock".to_string(), "First story".to_string()),
+                    ("Second".to_string(), "Second".to_string()),
+                ]
+            );
+            assert!(db.get_all_items_for_feed(1).await.unwrap().is_empty());
+
+            fetcher.refresh_all_feeds().await.unwrap();
+            let items = db.get_all_items_for_feed(1).await.unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].title, "First story");
+        }
+
+        #[tokio::test]
+        async fn test_links_are_canonicalized_and_original_kept() {
+            let server = MockServer::start().await;
+            let rss = RSS
+                .replace(
+                    "https://mock.example.com/1</link>",
+                    "http://mock.example.com/1?utm_source=rss&amp;id=1</link>",
+                )
+                .replace(
+                    "<guid>2</guid>",
+                    "<guid>https://mock.example.com/2?fbclid=abc</guid>",
+                )
+                .replace(
+                    "https://mock.example.com/2</link>",
+                    "https://mock.example.com/2?fbclid=abc</link>",
+                );
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(rss))
+                .mount(&server)
+                .await;
+
+            let url = format!("{}/rss", server.uri());
+            let (fetcher, db) = create_fetcher(url.clone()).await;
+            let config = Config::from_str(&format!(
+                r#"
+                [links]
+                strip_params = ["utm_*", "fbclid"]
+                rewrites = [{{ domain = "mock.example.com", https = true }}]
+
+                [[feeds]]
+                name = "Mock"
+                url = "{}"
+                "#,
+                url
+            ))
+            .unwrap();
+            fetcher.configure_feeds(&config).await.unwrap();
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            let items = db.get_all_items_for_feed(1).await.unwrap();
+            let first = items.iter().find(|item| item.guid == "1").unwrap();
+            assert_eq!(first.link, "https://mock.example.com/1?id=1");
+            assert_eq!(
+                first.original_link.as_deref(),
+                Some("http://mock.example.com/1?utm_source=rss&id=1")
+            );
+            // The feed's own id stays the key, even when it is the link
+            let second = items.iter().find(|item| item.title == "Second").unwrap();
+            assert_eq!(second.guid, "https://mock.example.com/2?fbclid=abc");
+            assert_eq!(second.link, "https://mock.example.com/2");
+
+            // Without the settings, links are stored as given again
+            let config = Config::from_str(&format!(
+                "[[feeds]]\nname = \"Mock\"\nurl = \"{}\"",
+                url
+            ))
+            .unwrap();
+            fetcher.configure_feeds(&config).await.unwrap();
+            fetcher.refresh_all_feeds().await.unwrap();
+            let items = db.get_all_items_for_feed(1).await.unwrap();
+            assert_eq!(items.len(), 2);
+            let first = items.iter().find(|item| item.guid == "1").unwrap();
+            assert_eq!(first.link, "http://mock.example.com/1?utm_source=rss&id=1");
+            assert_eq!(first.original_link, None);
+            // Changing the clean-up settings is not an edit to the items
+            assert!(items.iter().all(|item| item.revision_count == 0));
+        }
+
+        #[tokio::test]
+        async fn test_http_error_is_logged() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(503))
+                .mount(&server)
+                .await;
+
+            let (fetcher, db) = create_fetcher(format!("{}/rss", server.uri())).await;
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            let log = db.get_fetch_log(1, since()).await.unwrap();
+            assert_eq!(log[0].http_status, Some(503));
+            assert_eq!(log[0].error_category.as_deref(), Some("http"));
+
+            let feed = db.get_feed(1).await.unwrap().unwrap();
+            assert_eq!(feed.last_error.as_deref(), Some("HTTP 503 Service Unavailable"));
+        }
+
+        #[tokio::test]
+        async fn test_parse_error_is_logged() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_string("not a feed"))
+                .mount(&server)
+                .await;
+
+            let (fetcher, db) = create_fetcher(format!("{}/rss", server.uri())).await;
+            fetcher.refresh_all_feeds().await.unwrap();
+
+            let log = db.get_fetch_log(1, since()).await.unwrap();
+            assert_eq!(log[0].error_category.as_deref(), Some("parse"));
+            assert_eq!(log[0].bytes, Some(10));
+        }
+
+        #[test]
+        fn test_only_database_errors_are_categorized_as_database() {
+            let database = FetchError::Database(anyhow::anyhow!("database is locked"));
+            assert_eq!(database.category(), "database");
+            let other: FetchError = anyhow::anyhow!("regex parse error").into();
+            assert_eq!(other.category(), "other");
+        }
+
+        #[tokio::test]
+        async fn test_preview_stores_nothing() {
+            let server = MockServer::start().await;
+            let rss = RSS.replace(
+                "<guid>2</guid>",
+                "<guid>2</guid><comments>https://mock.example.com/2#comments</comments>",
+            );
+            Mock::given(method("GET"))
+                .and(path("/new"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(rss))
+                .mount(&server)
+                .await;
+
+            let (fetcher, db) = create_fetcher(format!("{}/rss", server.uri())).await;
+            let preview = fetcher.preview(&format!("{}/new", server.uri())).await.unwrap();
+
+            assert_eq!(preview.title.as_deref(), Some("Mock"));
+            assert_eq!(preview.homepage_url.as_deref(), Some("https://mock.example.com/"));
+            assert_eq!(preview.entry_count, 2);
+            assert_eq!(preview.discussion_count, 1);
+            assert_eq!(preview.items[0].title, "First");
+            assert!(preview.items[0].discussion_link.is_none());
+            assert_eq!(
+                preview.items[1].discussion_link.as_deref(),
+                Some("https://mock.example.com/2#comments")
+            );
+
+            assert_eq!(db.get_all_feeds().await.unwrap().len(), 1);
+            assert!(db.get_all_items_for_feed(1).await.unwrap().is_empty());
+            assert!(db.get_fetch_log(1, since()).await.unwrap().is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_preview_reports_http_errors() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(404))
+                .mount(&server)
+                .await;
+
+            let (fetcher, _db) = create_fetcher(format!("{}/rss", server.uri())).await;
+            let err = fetcher.preview(&format!("{}/rss", server.uri())).await.unwrap_err();
+
+            assert_eq!(err.to_string(), "HTTP 404 Not Found");
+        }
+    }
+
     // Tests for extract_homepage_url
     mod extract_homepage_url_tests {
         use super::*;
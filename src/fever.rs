@@ -0,0 +1,446 @@
+//! Fever API for mobile reader apps, served at `/fever/`.
+//!
+//! Apps sign in with the `[reader_api]` credentials, which they send as
+//! `api_key = md5("username:password")`. Feeds and items keep their database
+//! ids; groups are numbered in dashboard order, starting at 1.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Form, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use quick_xml::escape::escape;
+use serde_json::{json, Map, Value};
+
+use crate::config::{GroupConfig, ReaderApiConfig};
+use crate::db::{Feed, Item};
+use crate::routes::{constant_time_eq, group_feeds, AppError, AppState};
+
+pub const API_VERSION: u32 = 3;
+
+/// Items per `items` request, as the API specifies
+pub const ITEMS_PER_REQUEST: i64 = 50;
+
+/// Every feed shares one blank favicon
+const FAVICON_ID: i64 = 1;
+const FAVICON_DATA: &str =
+    "image/gif;base64,R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7";
+
+/// Routes of the API; apps are set up with either form of the path
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/fever/", get(fever).post(fever))
+        .route("/fever", get(fever).post(fever))
+}
+
+/// The key apps derive from `credentials`
+pub fn api_key(credentials: &ReaderApiConfig) -> String {
+    format!(
+        "{:x}",
+        md5::compute(format!("{}:{}", credentials.username, credentials.password))
+    )
+}
+
+/// Handle one API call. Arguments may come in the query string or the form body;
+/// apps name what they want as bare query arguments, as in `?api&items&since_id=7`.
+pub async fn fever(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HashMap<String, String>>,
+    form: Option<Form<HashMap<String, String>>>,
+) -> Result<Response, AppError> {
+    let config = state.reloader.config().await;
+    let Some(credentials) = &config.reader_api else {
+        return Ok((StatusCode::NOT_FOUND, "Not found").into_response());
+    };
+    let mut args = query;
+    if let Some(Form(form)) = form {
+        args.extend(form);
+    }
+
+    let mut response = Map::new();
+    response.insert("api_version".to_string(), json!(API_VERSION));
+    let key = args.get("api_key").map(|key| key.to_ascii_lowercase()).unwrap_or_default();
+    if !constant_time_eq(key.as_bytes(), api_key(credentials).as_bytes()) {
+        response.insert("auth".to_string(), json!(0));
+        return Ok(Json(response).into_response());
+    }
+    response.insert("auth".to_string(), json!(1));
+
+    let groups: Vec<GroupConfig> = config.sorted_groups().into_iter().cloned().collect();
+    let feeds = state.db.get_all_feeds().await?;
+    let last_refreshed = feeds
+        .iter()
+        .filter_map(|feed| feed.last_fetched.as_deref().and_then(parse_time))
+        .max();
+    response.insert(
+        "last_refreshed_on_time".to_string(),
+        json!(last_refreshed.map_or(0, |time| time.timestamp())),
+    );
+    let (_, sections) = group_feeds(feeds.clone(), &groups);
+    let argument = |name: &str| args.get(name).map(String::as_str);
+
+    // A mark answers with the list it changed; one that names nothing is refused
+    // before anything is written
+    let (mut marked_read, mut marked_saved) = (false, false);
+    if let Some(mark) = argument("mark") {
+        let Some(id) = argument("id").and_then(|id| id.parse::<i64>().ok()) else {
+            return Ok((StatusCode::BAD_REQUEST, "`mark` needs a numeric `id`").into_response());
+        };
+        let before = argument("before")
+            .and_then(|before| before.parse().ok())
+            .and_then(|before| DateTime::from_timestamp(before, 0));
+        match (mark, argument("as").unwrap_or_default()) {
+            ("item", "read" | "unread") => {
+                state.db.set_items_read(&[id], argument("as") == Some("read")).await?;
+                marked_read = true;
+            }
+            ("item", "saved" | "unsaved") => {
+                state.db.set_items_saved(&[id], argument("as") == Some("saved")).await?;
+                marked_saved = true;
+            }
+            ("feed", "read") => {
+                state.db.mark_feeds_read(&[id], before).await?;
+                marked_read = true;
+            }
+            ("group", "read") => {
+                // Group 0 is every feed
+                let feed_ids: Vec<i64> = match id {
+                    0 => feeds.iter().map(|feed| feed.id).collect(),
+                    id => usize::try_from(id - 1)
+                        .ok()
+                        .and_then(|index| sections.get(index))
+                        .map(|(_, members)| members.iter().map(|feed| feed.id).collect())
+                        .unwrap_or_default(),
+                };
+                state.db.mark_feeds_read(&feed_ids, before).await?;
+                marked_read = true;
+            }
+            (mark, marked_as) => {
+                let message = format!("cannot mark `{}` as `{}`", mark, marked_as);
+                return Ok((StatusCode::BAD_REQUEST, message).into_response());
+            }
+        }
+    }
+    let wants = |name: &str| args.contains_key(name);
+
+    if wants("groups") || wants("feeds") {
+        let feeds_groups: Vec<Value> = sections
+            .iter()
+            .zip(1..)
+            .map(|((_, members), id)| {
+                let feed_ids: Vec<i64> = members.iter().map(|feed| feed.id).collect();
+                json!({ "group_id": id, "feed_ids": join_ids(&feed_ids) })
+            })
+            .collect();
+        if wants("groups") {
+            let groups: Vec<Value> = sections
+                .iter()
+                .zip(1..)
+                .map(|((section, _), id)| json!({ "id": id, "title": section.name }))
+                .collect();
+            response.insert("groups".to_string(), json!(groups));
+        }
+        if wants("feeds") {
+            let feeds: Vec<Value> = feeds.iter().map(feed_json).collect();
+            response.insert("feeds".to_string(), json!(feeds));
+        }
+        response.insert("feeds_groups".to_string(), json!(feeds_groups));
+    }
+
+    if wants("favicons") {
+        response.insert(
+            "favicons".to_string(),
+            json!([{ "id": FAVICON_ID, "data": FAVICON_DATA }]),
+        );
+    }
+
+    if wants("items") {
+        let id = |name: &str| argument(name).and_then(|id| id.parse::<i64>().ok());
+        let items = if let Some(ids) = argument("with_ids") {
+            let ids: Vec<i64> = ids
+                .split(',')
+                .filter_map(|id| id.trim().parse().ok())
+                .take(ITEMS_PER_REQUEST as usize)
+                .collect();
+            state.db.get_items_by_ids(&ids).await?
+        } else if let Some(max_id) = id("max_id") {
+            // 0 asks for the newest items
+            let before = if max_id > 0 { max_id } else { i64::MAX };
+            state.db.get_items_before_id(before, ITEMS_PER_REQUEST).await?
+        } else {
+            let since_id = id("since_id").unwrap_or(0);
+            state.db.get_items_after_id(since_id, ITEMS_PER_REQUEST).await?
+        };
+        let items: Vec<Value> = items.iter().map(|item| item_json(item, &feeds)).collect();
+        response.insert("items".to_string(), json!(items));
+        response.insert(
+            "total_items".to_string(),
+            json!(state.db.get_total_item_count().await?),
+        );
+    }
+
+    if wants("links") {
+        response.insert("links".to_string(), json!([]));
+    }
+
+    if wants("unread_item_ids") || marked_read {
+        let ids = state.db.get_unread_item_ids().await?;
+        response.insert("unread_item_ids".to_string(), json!(join_ids(&ids)));
+    }
+
+    if wants("saved_item_ids") || marked_saved {
+        let ids = state.db.get_saved_item_ids().await?;
+        response.insert("saved_item_ids".to_string(), json!(join_ids(&ids)));
+    }
+
+    Ok(Json(response).into_response())
+}
+
+fn feed_json(feed: &Feed) -> Value {
+    json!({
+        "id": feed.id,
+        "favicon_id": FAVICON_ID,
+        "title": feed.name,
+        "url": feed.public_url(),
+        "site_url": feed.homepage_url.as_deref().unwrap_or_default(),
+        "is_spark": 0,
+        "last_updated_on_time": timestamp(feed.last_fetched.as_deref()),
+    })
+}
+
+fn item_json(item: &Item, feeds: &[Feed]) -> Value {
+    json!({
+        "id": item.id,
+        "feed_id": item.feed_id,
+        "title": item.title,
+        "author": "",
+        "html": item_html(item),
+        "url": item.link,
+        "is_saved": i32::from(item.is_saved),
+        "is_read": i32::from(item.is_read),
+        "created_on_time": item_timestamp(item, feeds),
+    })
+}
+
+/// Body shown for an item: only its title and links are stored
+pub(crate) fn item_html(item: &Item) -> String {
+    let mut html = format!(
+        "<p><a href=\"{}\">{}</a></p>",
+        escape(&item.link),
+        escape(&item.title)
+    );
+    if let Some(discussion) = &item.discussion_link {
+        html.push_str(&format!("<p><a href=\"{}\">Discussion</a></p>", escape(discussion)));
+    }
+    html
+}
+
+/// Unix time an item was published; undated items were new when their feed
+/// was last fetched
+pub(crate) fn item_timestamp(item: &Item, feeds: &[Feed]) -> i64 {
+    match item.published.as_deref() {
+        Some(published) => timestamp(Some(published)),
+        None => feeds
+            .iter()
+            .find(|feed| feed.id == item.feed_id)
+            .map_or(0, |feed| timestamp(feed.last_fetched.as_deref())),
+    }
+}
+
+pub(crate) fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Unix time of an RFC 3339 timestamp, or 0
+fn timestamp(value: Option<&str>) -> i64 {
+    value.and_then(parse_time).map_or(0, |time| time.timestamp())
+}
+
+fn join_ids(ids: &[i64]) -> String {
+    ids.iter().map(i64::to_string).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::Database;
+    use crate::test_support::{seed_reader_items, test_state, READER_CONFIG};
+    use axum::{body::Body, http::Request};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    /// md5("team:hunter2")
+    const API_KEY: &str = "40e496f7a8058010a38e6235122c58b6";
+
+    async fn create_test_app(config: &str) -> (Router, Arc<Database>) {
+        let state = test_state(Config::from_str(config).unwrap(), "feeds.toml").await;
+        let db = state.db.clone();
+        (router().with_state(Arc::new(state)), db)
+    }
+
+    /// POST like the apps do: wanted lists in the query string, the key in the form
+    async fn call(app: &Router, query: &str, form: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method("POST")
+            .uri(format!("/fever/?api&{}", query))
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from(form.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    async fn call_with_key(app: &Router, query: &str) -> Value {
+        let (status, body) = call(app, query, &format!("api_key={}", API_KEY)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["auth"], 1, "{}", query);
+        body
+    }
+
+    fn ids(items: &Value) -> Vec<i64> {
+        items.as_array().unwrap().iter().map(|item| item["id"].as_i64().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_authentication() {
+        let (app, _db) = create_test_app(READER_CONFIG).await;
+        let credentials = Config::from_str(READER_CONFIG).unwrap().reader_api.unwrap();
+        assert_eq!(api_key(&credentials), API_KEY);
+
+        let (status, body) = call(&app, "", "api_key=wrong").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "api_version": 3, "auth": 0 }));
+
+        let body = call_with_key(&app, "").await;
+        assert_eq!(body["api_version"], 3);
+        assert_eq!(body["last_refreshed_on_time"], 0);
+        // Some apps send the key upper case, or in the query string
+        let (_, body) = call(&app, &format!("api_key={}", API_KEY.to_uppercase()), "").await;
+        assert_eq!(body["auth"], 1);
+        let request = Request::builder()
+            .uri(format!("/fever?api&api_key={}", API_KEY))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+        let (app, _db) = create_test_app("feeds = []").await;
+        let (status, _) = call(&app, "", &format!("api_key={}", API_KEY)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_groups_and_feeds() {
+        let (app, db) = create_test_app(READER_CONFIG).await;
+        let feeds = seed_reader_items(&db).await;
+
+        let body = call_with_key(&app, "groups&feeds").await;
+        assert_eq!(body["groups"], json!([{ "id": 1, "title": "Tech" }]));
+        assert_eq!(
+            body["feeds_groups"],
+            json!([{ "group_id": 1, "feed_ids": format!("{},{}", feeds[0].id, feeds[1].id) }])
+        );
+        assert_eq!(body["feeds"].as_array().unwrap().len(), 3);
+        assert_eq!(body["feeds"][2]["title"], "NPR");
+        assert_eq!(body["feeds"][2]["url"], "https://npr.example.com/rss");
+        assert!(body["feeds"][2]["last_updated_on_time"].as_i64().unwrap() > 0);
+        assert!(body["last_refreshed_on_time"].as_i64().unwrap() > 0);
+
+        let favicon_id = body["feeds"][0]["favicon_id"].clone();
+        let body = call_with_key(&app, "favicons").await;
+        assert_eq!(body["favicons"][0]["id"], favicon_id);
+    }
+
+    #[tokio::test]
+    async fn test_items_paging() {
+        let (app, db) = create_test_app(READER_CONFIG).await;
+        seed_reader_items(&db).await;
+        let all = db.get_items_after_id(0, 10).await.unwrap();
+        let all: Vec<i64> = all.iter().map(|item| item.id).collect();
+
+        let body = call_with_key(&app, "items").await;
+        assert_eq!(ids(&body["items"]), all);
+        assert_eq!(body["total_items"], 6);
+
+        let item = &body["items"][3];
+        assert_eq!(item["title"], "Story 4");
+        assert_eq!(item["url"], "https://story.example/4");
+        assert_eq!(item["is_read"], 0);
+        assert!(item["html"].as_str().unwrap().contains("https://hn.example/item?id=3"));
+        assert!(item["created_on_time"].as_i64().unwrap() > 0);
+        // Undated items are dated by their feed's last fetch
+        assert!(body["items"][5]["created_on_time"].as_i64().unwrap() > 0);
+
+        let body = call_with_key(&app, &format!("items&since_id={}", all[3])).await;
+        assert_eq!(ids(&body["items"]), &all[4..]);
+        let body = call_with_key(&app, &format!("items&max_id={}", all[2])).await;
+        assert_eq!(ids(&body["items"]), [all[1], all[0]]);
+        let body = call_with_key(&app, "items&max_id=0").await;
+        assert_eq!(ids(&body["items"])[0], all[5]);
+        let query = format!("items&with_ids={},{},999", all[4], all[0]);
+        let body = call_with_key(&app, &query).await;
+        assert_eq!(ids(&body["items"]), [all[0], all[4]]);
+    }
+
+    #[tokio::test]
+    async fn test_marks() {
+        let (app, db) = create_test_app(READER_CONFIG).await;
+        let feeds = seed_reader_items(&db).await;
+        let all = db.get_items_after_id(0, 10).await.unwrap();
+        let all: Vec<i64> = all.iter().map(|item| item.id).collect();
+        let mark = |query: String| format!("api_key={}&{}", API_KEY, query);
+
+        let (_, body) = call(&app, "", &mark(format!("mark=item&as=read&id={}", all[0]))).await;
+        assert_eq!(body["unread_item_ids"], join_ids(&all[1..]));
+        let (_, body) = call(&app, "", &mark(format!("mark=item&as=saved&id={}", all[3]))).await;
+        assert_eq!(body["saved_item_ids"], all[3].to_string());
+        let body = call_with_key(&app, "saved_item_ids").await;
+        assert_eq!(body["saved_item_ids"], all[3].to_string());
+        let (_, body) = call(&app, "", &mark(format!("mark=item&as=unread&id={}", all[0]))).await;
+        assert_eq!(body["unread_item_ids"], join_ids(&all));
+
+        // Only what was published before the app last synced
+        let before = (Utc::now() - chrono::Duration::hours(15)).timestamp();
+        let query = format!("mark=feed&as=read&id={}&before={}", feeds[0].id, before);
+        let (_, body) = call(&app, "", &mark(query)).await;
+        assert_eq!(body["unread_item_ids"], join_ids(&all[2..]));
+
+        let query = format!("mark=group&as=read&id=1&before={}", Utc::now().timestamp());
+        let (_, body) = call(&app, "", &mark(query)).await;
+        assert_eq!(body["unread_item_ids"], all[5].to_string());
+
+        let query = format!("mark=group&as=read&id=0&before={}", Utc::now().timestamp());
+        let (_, body) = call(&app, "", &mark(query)).await;
+        assert_eq!(body["unread_item_ids"], "");
+        let body = call_with_key(&app, "items").await;
+        assert_eq!(body["items"][3]["is_saved"], 1);
+        assert_eq!(body["items"][3]["is_read"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_bad_marks_are_refused() {
+        let (app, db) = create_test_app(READER_CONFIG).await;
+        seed_reader_items(&db).await;
+        let mark = |query: &str| format!("api_key={}&{}", API_KEY, query);
+
+        let refused = ["mark=item&as=read", "mark=item&as=read&id=first", "mark=feed&as=saved&id=1"];
+        for query in refused {
+            let (status, body) = call(&app, "", &mark(query)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+            assert_eq!(body, Value::Null);
+        }
+        let body = call_with_key(&app, "unread_item_ids&saved_item_ids").await;
+        assert_eq!(body["unread_item_ids"].as_str().unwrap().split(',').count(), 6);
+        assert_eq!(body["saved_item_ids"], "");
+    }
+}
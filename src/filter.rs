@@ -0,0 +1,313 @@
+//! Keyword and regex matching of feed entries.
+//!
+//! Per-feed include/exclude rules are applied to entries before they are
+//! stored. The global highlight and mute lists are applied to stored items
+//! whenever the dashboard is rendered.
+
+use regex::Regex;
+
+use crate::config::{FeedConfig, FilterField, FilterRule, KeywordRule, KeywordsConfig};
+
+/// Label used in the fetch log for entries that no include rule matched
+pub const NO_INCLUDE_MATCH: &str = "include (no rule matched)";
+
+/// The parts of a feed entry that rules can match against
+#[derive(Debug, Default)]
+pub struct EntryFields<'a> {
+    pub title: &'a str,
+    pub link: &'a str,
+    pub authors: Vec<&'a str>,
+    pub categories: Vec<&'a str>,
+}
+
+enum Matcher {
+    /// Lowercased substring, or domain suffix for `FilterField::Domain`
+    Keyword(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// The matcher and how it is shown to users: `"keyword"` or `/regex/`
+    fn compile(
+        keyword: Option<&str>,
+        regex: Option<&str>,
+    ) -> Result<(Self, String), regex::Error> {
+        match (regex, keyword) {
+            (Some(pattern), _) => {
+                Ok((Matcher::Regex(Regex::new(pattern)?), format!("/{}/", pattern)))
+            }
+            (None, keyword) => {
+                let keyword = keyword.unwrap_or_default();
+                Ok((Matcher::Keyword(keyword.to_lowercase()), format!("\"{}\"", keyword)))
+            }
+        }
+    }
+
+    fn matches_text(&self, text: &str) -> bool {
+        match self {
+            Matcher::Keyword(keyword) => text.to_lowercase().contains(keyword),
+            Matcher::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    fn matches_domain(&self, domain: &str) -> bool {
+        match self {
+            Matcher::Keyword(keyword) => {
+                domain == keyword || domain.ends_with(&format!(".{}", keyword))
+            }
+            Matcher::Regex(regex) => regex.is_match(domain),
+        }
+    }
+}
+
+struct CompiledRule {
+    field: FilterField,
+    matcher: Matcher,
+    /// How the rule is shown in the fetch log, e.g. `exclude title "[Sponsor]"`
+    label: String,
+}
+
+impl CompiledRule {
+    fn compile(list: &str, rule: &FilterRule) -> Result<Self, regex::Error> {
+        let field = match rule.field {
+            FilterField::Title => "title",
+            FilterField::Domain => "domain",
+            FilterField::Author => "author",
+            FilterField::Category => "category",
+        };
+
+        let (matcher, shown) = Matcher::compile(rule.keyword.as_deref(), rule.regex.as_deref())?;
+        let label = format!("{} {} {}", list, field, shown);
+
+        Ok(Self {
+            field: rule.field,
+            matcher,
+            label,
+        })
+    }
+
+    fn matches(&self, entry: &EntryFields) -> bool {
+        let matcher = &self.matcher;
+        match self.field {
+            FilterField::Title => matcher.matches_text(entry.title),
+            FilterField::Domain => {
+                link_domain(entry.link).is_some_and(|d| matcher.matches_domain(&d))
+            }
+            FilterField::Author => entry.authors.iter().any(|a| matcher.matches_text(a)),
+            FilterField::Category => entry.categories.iter().any(|c| matcher.matches_text(c)),
+        }
+    }
+}
+
+fn link_domain(link: &str) -> Option<String> {
+    reqwest::Url::parse(link)
+        .ok()?
+        .host_str()
+        .map(|host| host.to_lowercase())
+}
+
+/// Compiled include/exclude rules for one feed
+#[derive(Default)]
+pub struct FeedFilter {
+    include: Vec<CompiledRule>,
+    exclude: Vec<CompiledRule>,
+}
+
+impl FeedFilter {
+    pub fn compile(feed: &FeedConfig) -> Result<Self, regex::Error> {
+        Ok(Self {
+            include: feed
+                .include
+                .iter()
+                .map(|rule| CompiledRule::compile("include", rule))
+                .collect::<Result<_, _>>()?,
+            exclude: feed
+                .exclude
+                .iter()
+                .map(|rule| CompiledRule::compile("exclude", rule))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Decide whether to keep an entry. Returns the label of the rule that
+    /// filtered it out, or `None` to keep it. Exclude rules win over include rules.
+    pub fn check(&self, entry: &EntryFields) -> Option<&str> {
+        if let Some(rule) = self.exclude.iter().find(|rule| rule.matches(entry)) {
+            return Some(&rule.label);
+        }
+        if !self.include.is_empty() && !self.include.iter().any(|rule| rule.matches(entry)) {
+            return Some(NO_INCLUDE_MATCH);
+        }
+        None
+    }
+}
+
+/// How an item should be shown on the dashboard
+#[derive(Debug, Default, PartialEq)]
+pub struct Emphasis {
+    pub highlighted: bool,
+    /// The mute rule that matched, shown on the collapsed item
+    pub muted_by: Option<String>,
+}
+
+/// Compiled highlight and mute lists, matched against item titles
+#[derive(Default)]
+pub struct Keywords {
+    highlight: Vec<Matcher>,
+    mute: Vec<(Matcher, String)>,
+}
+
+impl Keywords {
+    pub fn compile(config: &KeywordsConfig) -> Result<Self, regex::Error> {
+        let compile = |rule: &KeywordRule| {
+            Matcher::compile(rule.keyword.as_deref(), rule.regex.as_deref())
+        };
+        Ok(Self {
+            highlight: config
+                .highlight
+                .iter()
+                .map(|rule| compile(rule).map(|(matcher, _)| matcher))
+                .collect::<Result<_, _>>()?,
+            mute: config.mute.iter().map(compile).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Highlighting wins: an item matching both lists is highlighted, not muted
+    pub fn emphasis(&self, title: &str) -> Emphasis {
+        if self.highlight.iter().any(|m| m.matches_text(title)) {
+            return Emphasis {
+                highlighted: true,
+                muted_by: None,
+            };
+        }
+        Emphasis {
+            highlighted: false,
+            muted_by: self
+                .mute
+                .iter()
+                .find(|(m, _)| m.matches_text(title))
+                .map(|(_, label)| label.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn create_filter(rules: &str) -> FeedFilter {
+        let content = format!(
+            "[[feeds]]\nname = \"Test\"\nurl = \"https://test.com/rss\"\n{}",
+            rules
+        );
+        let config = Config::from_str(&content).unwrap();
+        FeedFilter::compile(&config.feeds[0]).unwrap()
+    }
+
+    fn entry<'a>(title: &'a str, link: &'a str) -> EntryFields<'a> {
+        EntryFields {
+            title,
+            link,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_empty_filter_keeps_everything() {
+        let filter = create_filter("");
+
+        assert!(filter.is_empty());
+        assert_eq!(filter.check(&entry("Anything", "https://test.com/1")), None);
+    }
+
+    #[test]
+    fn test_exclude_title_keyword_is_case_insensitive() {
+        let filter = create_filter(r#"exclude = [{ field = "title", keyword = "[sponsor]" }]"#);
+
+        assert_eq!(
+            filter.check(&entry("[Sponsor] Buy things", "https://test.com/1")),
+            Some("exclude title \"[sponsor]\"")
+        );
+        assert_eq!(filter.check(&entry("Real post", "https://test.com/2")), None);
+    }
+
+    #[test]
+    fn test_exclude_category_regex() {
+        let filter = create_filter(r#"exclude = [{ field = "category", regex = "^Deals?$" }]"#);
+        let mut deal = entry("Laptop deals", "https://test.com/1");
+        deal.categories = vec!["News", "Deals"];
+        let mut news = entry("Laptop review", "https://test.com/2");
+        news.categories = vec!["Reviews"];
+
+        assert_eq!(filter.check(&deal), Some("exclude category /^Deals?$/"));
+        assert_eq!(filter.check(&news), None);
+    }
+
+    #[test]
+    fn test_include_domain_matches_subdomains() {
+        let filter = create_filter(r#"include = [{ field = "domain", keyword = "example.com" }]"#);
+
+        assert_eq!(filter.check(&entry("A", "https://example.com/a")), None);
+        assert_eq!(filter.check(&entry("B", "https://blog.Example.com/b")), None);
+        assert_eq!(
+            filter.check(&entry("C", "https://notexample.com/c")),
+            Some(NO_INCLUDE_MATCH)
+        );
+    }
+
+    #[test]
+    fn test_exclude_wins_over_include() {
+        let filter = create_filter(
+            r#"
+            include = [{ field = "author", keyword = "gruber" }]
+            exclude = [{ field = "title", keyword = "sponsor" }]
+            "#,
+        );
+        let mut sponsored = entry("Sponsor: Foo", "https://test.com/1");
+        sponsored.authors = vec!["John Gruber"];
+
+        assert_eq!(
+            filter.check(&sponsored),
+            Some("exclude title \"sponsor\"")
+        );
+    }
+
+    #[test]
+    fn test_keyword_emphasis() {
+        let config = Config::from_str(
+            r#"
+            feeds = []
+
+            [keywords]
+            highlight = [{ keyword = "rust" }]
+            mute = [{ regex = "(?i)\\bcrypto" }, { keyword = "election" }]
+            "#,
+        )
+        .unwrap();
+        let keywords = Keywords::compile(&config.keywords).unwrap();
+
+        assert_eq!(keywords.emphasis("Plain headline"), Emphasis::default());
+        assert!(keywords.emphasis("Rust 2.0 released").highlighted);
+        assert_eq!(
+            keywords.emphasis("Crypto winter").muted_by.as_deref(),
+            Some("/(?i)\\bcrypto/")
+        );
+        assert_eq!(
+            keywords.emphasis("Election results").muted_by.as_deref(),
+            Some("\"election\"")
+        );
+        // Highlighting wins over muting
+        assert_eq!(
+            keywords.emphasis("Rust and the election"),
+            Emphasis {
+                highlighted: true,
+                muted_by: None
+            }
+        );
+    }
+}
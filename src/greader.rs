@@ -0,0 +1,822 @@
+//! Google Reader API, the subset FreshRSS and Miniflux implement, for reader
+//! apps such as NetNewsWire and FeedMe.
+//!
+//! Apps sign in at `/accounts/ClientLogin` with the `[reader_api]` credentials
+//! and make every other call under `/reader/api/0`. Streams are named the Google
+//! Reader way: `feed/<id>`, `user/-/label/<group>` and the
+//! `user/-/state/com.google/...` states.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::config::{GroupConfig, ReaderApiConfig};
+use crate::db::{Feed, Item, ItemFilter};
+use crate::fever::{item_html, item_timestamp, parse_time};
+use crate::routes::{constant_time_eq, group_feeds, AppError, AppState};
+
+const READING_LIST: &str = "user/-/state/com.google/reading-list";
+const READ: &str = "user/-/state/com.google/read";
+const KEPT_UNREAD: &str = "user/-/state/com.google/kept-unread";
+const STARRED: &str = "user/-/state/com.google/starred";
+const LABEL_PREFIX: &str = "user/-/label/";
+/// Long form of item ids; the short form is the decimal database id
+const ITEM_ID_PREFIX: &str = "tag:google.com,2005:reader/item/";
+
+/// Items per stream request when the app doesn't ask for a number
+pub const DEFAULT_STREAM_ITEMS: i64 = 20;
+/// Most items a stream request may ask for
+pub const MAX_STREAM_ITEMS: i64 = 1000;
+
+pub fn router() -> Router<Arc<AppState>> {
+    let api = Router::new()
+        .route("/token", get(token))
+        .route("/user-info", get(user_info))
+        .route("/subscription/list", get(subscription_list))
+        .route("/tag/list", get(tag_list))
+        .route("/unread-count", get(unread_count))
+        .route("/stream/items/ids", get(stream_item_ids))
+        .route("/stream/items/contents", get(item_contents).post(item_contents))
+        .route("/stream/contents", get(stream_contents).post(stream_contents))
+        .route("/stream/contents/*stream", get(stream_contents).post(stream_contents))
+        .route("/edit-tag", post(edit_tag))
+        .route("/mark-all-as-read", post(mark_all_as_read));
+
+    Router::new()
+        .route("/accounts/ClientLogin", get(client_login).post(client_login))
+        .nest("/reader/api/0", api)
+}
+
+/// Purpose of the token apps get from `ClientLogin` and send back as
+/// `Authorization: GoogleLogin auth=<token>`
+const AUTH_TOKEN: &str = "reader-auth";
+/// Purpose of the token apps get from `/token` and send along with edits
+const EDIT_TOKEN: &str = "reader-edit";
+/// How long an app stays signed in; apps sign in again when a call is refused
+const AUTH_TOKEN_LIFETIME: chrono::Duration = chrono::Duration::days(30);
+/// How long an edit token lasts
+const EDIT_TOKEN_LIFETIME: chrono::Duration = chrono::Duration::days(1);
+
+/// What tokens are tied to, so changing the username or password signs apps out
+fn token_secret(credentials: &ReaderApiConfig) -> String {
+    format!("{}:{}", credentials.username, credentials.password)
+}
+
+/// A request carrying a valid token
+pub struct ReaderUser {
+    pub credentials: ReaderApiConfig,
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ReaderUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(credentials) = state.reloader.config().await.reader_api.clone() else {
+            return Err((StatusCode::NOT_FOUND, "Not found").into_response());
+        };
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("GoogleLogin auth="))
+            .unwrap_or_default();
+        if state.signer.verify(AUTH_TOKEN, &token_secret(&credentials), token.trim()) {
+            Ok(Self { credentials })
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "Unauthorized").into_response())
+        }
+    }
+}
+
+/// Arguments from the query string and the form body; names may repeat
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    fn new(query: Vec<(String, String)>, form: Option<Form<Vec<(String, String)>>>) -> Self {
+        let mut params = query;
+        if let Some(Form(form)) = form {
+            params.extend(form);
+        }
+        Self(params)
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn number(&self, name: &str) -> Option<i64> {
+        self.get(name).and_then(|value| value.parse().ok())
+    }
+}
+
+pub async fn client_login(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<Vec<(String, String)>>,
+    form: Option<Form<Vec<(String, String)>>>,
+) -> Response {
+    let Some(credentials) = state.reloader.config().await.reader_api.clone() else {
+        return (StatusCode::NOT_FOUND, "Not found").into_response();
+    };
+    let params = Params::new(query, form);
+    let matches = |name: &str, expected: &str| {
+        constant_time_eq(params.get(name).unwrap_or_default().as_bytes(), expected.as_bytes())
+    };
+    // Both are checked, so a wrong username takes as long as a wrong password
+    let username_ok = matches("Email", &credentials.username);
+    let password_ok = matches("Passwd", &credentials.password);
+    if !(username_ok && password_ok) {
+        return (StatusCode::UNAUTHORIZED, "Error=BadAuthentication\n").into_response();
+    }
+
+    let token = state.signer.issue(AUTH_TOKEN, &token_secret(&credentials), AUTH_TOKEN_LIFETIME);
+    if params.get("output") == Some("json") {
+        Json(json!({ "SID": token, "LSID": token, "Auth": token })).into_response()
+    } else {
+        format!("SID={}\nLSID={}\nAuth={}\n", token, token, token).into_response()
+    }
+}
+
+/// Token for edit requests. Edits are authorized by the `Authorization` header
+/// alone, so the token isn't checked when it comes back.
+pub async fn token(user: ReaderUser, State(state): State<Arc<AppState>>) -> String {
+    let secret = token_secret(&user.credentials);
+    state.signer.issue(EDIT_TOKEN, &secret, EDIT_TOKEN_LIFETIME)
+}
+
+pub async fn user_info(user: ReaderUser) -> Json<Value> {
+    Json(json!({
+        "userId": "1",
+        "userName": user.credentials.username,
+        "userProfileId": "1",
+        "userEmail": "",
+    }))
+}
+
+fn label_id(group: &str) -> String {
+    format!("{}{}", LABEL_PREFIX, group)
+}
+
+pub async fn subscription_list(
+    _user: ReaderUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Value>, AppError> {
+    let subscriptions: Vec<Value> = state
+        .db
+        .get_all_feeds()
+        .await?
+        .iter()
+        .map(|feed| {
+            let categories: Vec<Value> = feed
+                .group_name
+                .iter()
+                .map(|group| json!({ "id": label_id(group), "label": group }))
+                .collect();
+            json!({
+                "id": format!("feed/{}", feed.id),
+                "title": feed.name,
+                "categories": categories,
+                "url": feed.public_url(),
+                "htmlUrl": feed.homepage_url.as_deref().unwrap_or_default(),
+                "iconUrl": "",
+            })
+        })
+        .collect();
+    Ok(Json(json!({ "subscriptions": subscriptions })))
+}
+
+/// Groups with feeds, in dashboard order
+async fn sections(state: &AppState) -> anyhow::Result<Vec<(String, Vec<Feed>)>> {
+    let config = state.reloader.config().await;
+    let groups: Vec<GroupConfig> = config.sorted_groups().into_iter().cloned().collect();
+    let (_, sections) = group_feeds(state.db.get_all_feeds().await?, &groups);
+    Ok(sections
+        .into_iter()
+        .map(|(section, members)| (section.name, members))
+        .collect())
+}
+
+pub async fn tag_list(
+    _user: ReaderUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Value>, AppError> {
+    let mut tags = vec![json!({ "id": STARRED })];
+    for (name, _) in sections(&state).await? {
+        tags.push(json!({ "id": label_id(&name), "type": "folder" }));
+    }
+    Ok(Json(json!({ "tags": tags })))
+}
+
+pub async fn unread_count(
+    _user: ReaderUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Value>, AppError> {
+    let feeds = state.db.get_all_feeds().await?;
+    // Per stream: count and newest publication date
+    let mut totals: BTreeMap<String, (i64, Option<String>)> = BTreeMap::new();
+    let mut add = |stream: String, count: i64, newest: &Option<String>| {
+        let total = totals.entry(stream).or_default();
+        total.0 += count;
+        total.1 = total.1.clone().max(newest.clone());
+    };
+    for (feed_id, count, newest) in state.db.get_unread_counts().await? {
+        add(format!("feed/{}", feed_id), count, &newest);
+        let feed = feeds.iter().find(|feed| feed.id == feed_id);
+        if let Some(group) = feed.and_then(|feed| feed.group_name.as_ref()) {
+            add(label_id(group), count, &newest);
+        }
+        add(READING_LIST.to_string(), count, &newest);
+    }
+
+    let counts: Vec<Value> = totals
+        .into_iter()
+        .map(|(id, (count, newest))| {
+            let newest = newest.as_deref().and_then(parse_time).map_or(0, |time| time.timestamp());
+            json!({
+                "id": id,
+                "count": count,
+                "newestItemTimestampUsec": (newest * 1_000_000).to_string(),
+            })
+        })
+        .collect();
+    Ok(Json(json!({ "max": MAX_STREAM_ITEMS, "unreadcounts": counts })))
+}
+
+/// Apps may name the user by number instead of `-`
+fn normalize(id: &str) -> String {
+    match id.strip_prefix("user/").and_then(|rest| rest.split_once('/')) {
+        Some((_, rest)) => format!("user/-/{}", rest),
+        None => id.to_string(),
+    }
+}
+
+/// A stream id, as named in `s` or the path
+enum Stream {
+    ReadingList,
+    Read,
+    Starred,
+    Feed(i64),
+    Label(String),
+}
+
+impl Stream {
+    fn parse(id: &str) -> Option<Self> {
+        let id = normalize(id);
+        match id.as_str() {
+            READING_LIST => Some(Self::ReadingList),
+            READ => Some(Self::Read),
+            STARRED => Some(Self::Starred),
+            _ => {
+                if let Some(feed_id) = id.strip_prefix("feed/") {
+                    feed_id.parse().ok().map(Self::Feed)
+                } else {
+                    id.strip_prefix(LABEL_PREFIX).map(|label| Self::Label(label.to_string()))
+                }
+            }
+        }
+    }
+
+    /// Ids of the feeds the stream draws from; `None` for every feed
+    fn feed_ids(&self, feeds: &[Feed]) -> Option<Vec<i64>> {
+        match self {
+            Self::ReadingList | Self::Read | Self::Starred => None,
+            Self::Feed(id) => Some(vec![*id]),
+            Self::Label(label) => Some(
+                feeds
+                    .iter()
+                    .filter(|feed| feed.group_name.as_deref() == Some(label))
+                    .map(|feed| feed.id)
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn unknown_stream() -> Response {
+    (StatusCode::BAD_REQUEST, "Unknown stream").into_response()
+}
+
+/// A page of the stream `stream_id`, filtered by `xt` (exclude), `it` (include),
+/// `ot` and `nt` (published after, before), ordered by `r`, and resumed from the
+/// continuation `c`. Returns the items and the continuation of the next page.
+async fn stream_page(
+    state: &AppState,
+    feeds: &[Feed],
+    stream_id: &str,
+    params: &Params,
+) -> Result<Result<(Vec<Item>, Option<String>), Response>, AppError> {
+    let Some(stream) = Stream::parse(stream_id) else {
+        return Ok(Err(unknown_stream()));
+    };
+    let limit = params
+        .number("n")
+        .unwrap_or(DEFAULT_STREAM_ITEMS)
+        .clamp(1, MAX_STREAM_ITEMS);
+    let time = |name: &str| params.number(name).and_then(|secs| DateTime::from_timestamp(secs, 0));
+
+    let mut filter = ItemFilter {
+        feed_ids: stream.feed_ids(feeds),
+        published_since: time("ot"),
+        published_before: time("nt"),
+        after_id: params.number("c"),
+        oldest_first: params.get("r") == Some("o"),
+        limit: limit + 1,
+        ..Default::default()
+    };
+    match stream {
+        Stream::Read => filter.is_read = Some(true),
+        Stream::Starred => filter.is_saved = Some(true),
+        _ => {}
+    }
+    for state_id in params.all("xt").filter_map(Stream::parse) {
+        match state_id {
+            Stream::Read => filter.is_read = Some(false),
+            Stream::Starred => filter.is_saved = Some(false),
+            _ => {}
+        }
+    }
+    for state_id in params.all("it").filter_map(Stream::parse) {
+        match state_id {
+            Stream::Read => filter.is_read = Some(true),
+            Stream::Starred => filter.is_saved = Some(true),
+            _ => {}
+        }
+    }
+
+    let mut items = state.db.find_items(&filter).await?;
+    let continuation = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(|item| item.id.to_string())
+    } else {
+        None
+    };
+    Ok(Ok((items, continuation)))
+}
+
+fn long_item_id(id: i64) -> String {
+    format!("{}{:016x}", ITEM_ID_PREFIX, id)
+}
+
+/// Parse an item id in the long, hexadecimal form or the short, decimal one
+fn parse_item_id(id: &str) -> Option<i64> {
+    match id.strip_prefix(ITEM_ID_PREFIX) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok().map(|id| id as i64),
+        None => id.parse().ok(),
+    }
+}
+
+fn item_json(item: &Item, feeds: &[Feed]) -> Value {
+    let feed = feeds.iter().find(|feed| feed.id == item.feed_id);
+    let mut categories = vec![READING_LIST.to_string()];
+    if let Some(group) = feed.and_then(|feed| feed.group_name.as_ref()) {
+        categories.push(label_id(group));
+    }
+    if item.is_read {
+        categories.push(READ.to_string());
+    }
+    if item.is_saved {
+        categories.push(STARRED.to_string());
+    }
+    let time = item_timestamp(item, feeds);
+
+    json!({
+        "id": long_item_id(item.id),
+        "crawlTimeMsec": (time * 1000).to_string(),
+        "timestampUsec": (time * 1_000_000).to_string(),
+        "published": time,
+        "updated": time,
+        "title": item.title,
+        "canonical": [{ "href": item.link }],
+        "alternate": [{ "href": item.link, "type": "text/html" }],
+        "summary": { "direction": "ltr", "content": item_html(item) },
+        "author": "",
+        "categories": categories,
+        "origin": {
+            "streamId": format!("feed/{}", item.feed_id),
+            "title": feed.map(|feed| feed.name.as_str()).unwrap_or_default(),
+            "htmlUrl": feed.and_then(|feed| feed.homepage_url.as_deref()).unwrap_or_default(),
+        },
+    })
+}
+
+pub async fn stream_item_ids(
+    _user: ReaderUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    let params = Params::new(query, None);
+    let feeds = state.db.get_all_feeds().await?;
+    let stream_id = params.get("s").unwrap_or(READING_LIST);
+    let (items, continuation) = match stream_page(&state, &feeds, stream_id, &params).await? {
+        Ok(page) => page,
+        Err(response) => return Ok(response),
+    };
+
+    let refs: Vec<Value> = items
+        .iter()
+        .map(|item| {
+            json!({
+                "id": item.id.to_string(),
+                "directStreamIds": [],
+                "timestampUsec": (item_timestamp(item, &feeds) * 1_000_000).to_string(),
+            })
+        })
+        .collect();
+    let mut body = json!({ "itemRefs": refs });
+    if let Some(continuation) = continuation {
+        body["continuation"] = json!(continuation);
+    }
+    Ok(Json(body).into_response())
+}
+
+pub async fn stream_contents(
+    _user: ReaderUser,
+    State(state): State<Arc<AppState>>,
+    path: Option<Path<String>>,
+    Query(query): Query<Vec<(String, String)>>,
+    form: Option<Form<Vec<(String, String)>>>,
+) -> Result<Response, AppError> {
+    let params = Params::new(query, form);
+    let feeds = state.db.get_all_feeds().await?;
+    let stream_id = match &path {
+        Some(Path(stream)) => stream.as_str(),
+        None => params.get("s").unwrap_or(READING_LIST),
+    };
+    let (items, continuation) = match stream_page(&state, &feeds, stream_id, &params).await? {
+        Ok(page) => page,
+        Err(response) => return Ok(response),
+    };
+
+    let items: Vec<Value> = items.iter().map(|item| item_json(item, &feeds)).collect();
+    let mut body = json!({
+        "id": stream_id,
+        "updated": Utc::now().timestamp(),
+        "items": items,
+    });
+    if let Some(continuation) = continuation {
+        body["continuation"] = json!(continuation);
+    }
+    Ok(Json(body).into_response())
+}
+
+pub async fn item_contents(
+    _user: ReaderUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<Vec<(String, String)>>,
+    form: Option<Form<Vec<(String, String)>>>,
+) -> Result<Json<Value>, AppError> {
+    let params = Params::new(query, form);
+    let ids: Vec<i64> = params.all("i").filter_map(parse_item_id).collect();
+    let feeds = state.db.get_all_feeds().await?;
+
+    let items: Vec<Value> = state
+        .db
+        .get_items_by_ids(&ids)
+        .await?
+        .iter()
+        .map(|item| item_json(item, &feeds))
+        .collect();
+    Ok(Json(json!({
+        "id": READING_LIST,
+        "updated": Utc::now().timestamp(),
+        "items": items,
+    })))
+}
+
+/// Add (`a`) or remove (`r`) the read and starred states of the items `i`.
+/// Labels can't be changed, as groups come from the configuration.
+pub async fn edit_tag(
+    _user: ReaderUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<Vec<(String, String)>>,
+    form: Option<Form<Vec<(String, String)>>>,
+) -> Result<&'static str, AppError> {
+    let params = Params::new(query, form);
+    let ids: Vec<i64> = params.all("i").filter_map(parse_item_id).collect();
+
+    let changes = params
+        .all("a")
+        .map(|tag| (tag, true))
+        .chain(params.all("r").map(|tag| (tag, false)));
+    for (tag, added) in changes {
+        match normalize(tag).as_str() {
+            READ => state.db.set_items_read(&ids, added).await?,
+            // Keeping an item unread is another way to say unread
+            KEPT_UNREAD => state.db.set_items_read(&ids, !added).await?,
+            STARRED => state.db.set_items_saved(&ids, added).await?,
+            _ => 0,
+        };
+    }
+    Ok("OK")
+}
+
+/// Mark every item of the stream `s` read, or only those published before `ts`
+/// (microseconds)
+pub async fn mark_all_as_read(
+    _user: ReaderUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<Vec<(String, String)>>,
+    form: Option<Form<Vec<(String, String)>>>,
+) -> Result<Response, AppError> {
+    let params = Params::new(query, form);
+    let feeds = state.db.get_all_feeds().await?;
+    let stream = params.get("s").and_then(Stream::parse);
+    let feed_ids = match &stream {
+        Some(Stream::ReadingList) => feeds.iter().map(|feed| feed.id).collect(),
+        Some(stream @ (Stream::Feed(_) | Stream::Label(_))) => {
+            stream.feed_ids(&feeds).unwrap_or_default()
+        }
+        _ => return Ok(unknown_stream()),
+    };
+    let before = params
+        .number("ts")
+        .and_then(|usec| DateTime::from_timestamp(usec / 1_000_000, 0));
+
+    state.db.mark_feeds_read(&feed_ids, before).await?;
+    Ok("OK".into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::Database;
+    use crate::test_support::{seed_reader_items, test_state, READER_CONFIG};
+    use axum::{body::Body, http::Request};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    /// The API, with the token of an app that signed in
+    struct TestApp {
+        router: Router,
+        token: String,
+    }
+
+    async fn create_test_app(config: &str) -> (TestApp, Arc<Database>) {
+        let state = test_state(Config::from_str(config).unwrap(), "feeds.toml").await;
+        let token = match &state.reloader.config().await.reader_api {
+            Some(credentials) => {
+                state.signer.issue(AUTH_TOKEN, &token_secret(credentials), AUTH_TOKEN_LIFETIME)
+            }
+            None => String::new(),
+        };
+        let db = state.db.clone();
+        let router = router().with_state(Arc::new(state));
+        (TestApp { router, token }, db)
+    }
+
+    async fn call(
+        app: &TestApp,
+        method: &str,
+        uri: &str,
+        form: &str,
+        token: Option<&str>,
+    ) -> (StatusCode, String) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/x-www-form-urlencoded");
+        if let Some(token) = token {
+            request = request.header("authorization", format!("GoogleLogin auth={}", token));
+        }
+        let request = request.body(Body::from(form.to_string())).unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    async fn get_json(app: &TestApp, uri: &str) -> Value {
+        let (status, body) = call(app, "GET", uri, "", Some(&app.token)).await;
+        assert_eq!(status, StatusCode::OK, "{}: {}", uri, body);
+        serde_json::from_str(&body).unwrap()
+    }
+
+    async fn post(app: &TestApp, uri: &str, form: &str) {
+        let (status, body) = call(app, "POST", uri, form, Some(&app.token)).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "OK"), "{} {}", uri, form);
+    }
+
+    fn item_ids(body: &Value) -> Vec<i64> {
+        body["itemRefs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["id"].as_str().unwrap().parse().unwrap())
+            .collect()
+    }
+
+    fn unread(body: &Value, id: &str) -> Option<i64> {
+        let counts = body["unreadcounts"].as_array().unwrap();
+        counts.iter().find(|count| count["id"] == id).map(|count| count["count"].as_i64().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_client_login() {
+        let (app, _db) = create_test_app(READER_CONFIG).await;
+
+        let (status, body) =
+            call(&app, "POST", "/accounts/ClientLogin", "Email=team&Passwd=wrong", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body, "Error=BadAuthentication\n");
+
+        let (status, body) =
+            call(&app, "POST", "/accounts/ClientLogin", "Email=team&Passwd=hunter2", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let token = body.lines().find_map(|line| line.strip_prefix("Auth=")).unwrap();
+        assert!(body.starts_with(&format!("SID={}\n", token)), "{}", body);
+        let uri = "/accounts/ClientLogin?Email=team&Passwd=hunter2&output=json";
+        let (_, body) = call(&app, "GET", uri, "", None).await;
+        let json_token = serde_json::from_str::<Value>(&body).unwrap()["Auth"].clone();
+
+        // Signed tokens, not the Fever key
+        let credentials = Config::from_str(READER_CONFIG).unwrap().reader_api.unwrap();
+        let key = crate::fever::api_key(&credentials);
+        assert!(!token.contains(&key) && !json_token.as_str().unwrap().contains(&key));
+        let user_info = "/reader/api/0/user-info";
+        for token in [token, json_token.as_str().unwrap()] {
+            let (status, body) = call(&app, "GET", user_info, "", Some(token)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["userName"], "team");
+        }
+        let (status, edit_token) = call(&app, "GET", "/reader/api/0/token", "", Some(token)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!edit_token.contains(&key));
+        let (status, _) = call(&app, "GET", user_info, "", Some(&edit_token)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let uri = "/reader/api/0/subscription/list";
+        assert_eq!(call(&app, "GET", uri, "", None).await.0, StatusCode::UNAUTHORIZED);
+        let fever_style = format!("team/{}", key);
+        let (status, _) = call(&app, "GET", uri, "", Some(&fever_style)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(get_json(&app, user_info).await["userName"], "team");
+
+        let (other, _db) = create_test_app("feeds = []").await;
+        let (status, _) =
+            call(&other, "POST", "/accounts/ClientLogin", "Email=team&Passwd=hunter2", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(call(&other, "GET", uri, "", Some(token)).await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_and_tags() {
+        let (app, db) = create_test_app(READER_CONFIG).await;
+        let feeds = seed_reader_items(&db).await;
+
+        let body = get_json(&app, "/reader/api/0/subscription/list?output=json").await;
+        let subscriptions = body["subscriptions"].as_array().unwrap();
+        assert_eq!(subscriptions.len(), 3);
+        let ars = &subscriptions[0];
+        assert_eq!(ars["id"], format!("feed/{}", feeds[0].id));
+        assert_eq!(ars["title"], "Ars");
+        assert_eq!(ars["url"], "https://ars.example.com/rss");
+        assert_eq!(ars["categories"], json!([{ "id": "user/-/label/Tech", "label": "Tech" }]));
+        assert_eq!(subscriptions[2]["categories"], json!([]));
+
+        let body = get_json(&app, "/reader/api/0/tag/list?output=json").await;
+        assert_eq!(
+            body["tags"],
+            json!([{ "id": STARRED }, { "id": "user/-/label/Tech", "type": "folder" }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streams() {
+        let (app, db) = create_test_app(READER_CONFIG).await;
+        let feeds = seed_reader_items(&db).await;
+
+        // Newest first, two at a time
+        let body = get_json(&app, "/reader/api/0/stream/items/ids?n=2").await;
+        assert_eq!(item_ids(&body), [6, 5]);
+        assert_eq!(body["continuation"], "5");
+        let body = get_json(&app, "/reader/api/0/stream/items/ids?n=2&c=5").await;
+        assert_eq!(item_ids(&body), [4, 3]);
+        let body = get_json(&app, "/reader/api/0/stream/items/ids?n=2&c=3").await;
+        assert_eq!(item_ids(&body), [2, 1]);
+        assert!(body.get("continuation").is_none());
+
+        // Labels, oldest first, and the user named by number
+        let uri = "/reader/api/0/stream/items/ids?s=user/1/label/Tech&r=o";
+        assert_eq!(item_ids(&get_json(&app, uri).await), [1, 2, 3, 4, 5]);
+        let uri = "/reader/api/0/stream/items/ids?s=user/-/label/Nope";
+        assert_eq!(call(&app, "GET", uri, "", Some(&app.token)).await.0, StatusCode::OK);
+        let uri = "/reader/api/0/stream/items/ids?s=bogus";
+        assert_eq!(call(&app, "GET", uri, "", Some(&app.token)).await.0, StatusCode::BAD_REQUEST);
+
+        // Contents of a feed, by path
+        let uri = format!("/reader/api/0/stream/contents/feed/{}?n=2", feeds[1].id);
+        let body = get_json(&app, &uri).await;
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        let item = &items[0];
+        assert_eq!(item["id"], "tag:google.com,2005:reader/item/0000000000000005");
+        assert_eq!(item["title"], "Story 5");
+        assert_eq!(item["alternate"][0]["href"], "https://story.example/5");
+        assert_eq!(item["origin"]["title"], "HN");
+        assert_eq!(item["categories"], json!([READING_LIST, "user/-/label/Tech"]));
+        let published = item["published"].as_i64().unwrap();
+        assert!((Utc::now().timestamp() - 5 * 3600 - published).abs() < 60);
+        assert_eq!(item["timestampUsec"], (published * 1_000_000).to_string());
+
+        // Published bounds; undated items are never too old
+        let since = (Utc::now() - chrono::Duration::minutes(330)).timestamp();
+        let uri = format!("/reader/api/0/stream/items/ids?ot={}", since);
+        assert_eq!(item_ids(&get_json(&app, &uri).await), [6, 5]);
+
+        // Items by id, in either form
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/reader/api/0/stream/items/contents",
+            "i=1&i=tag:google.com,2005:reader/item/0000000000000002",
+            Some(&app.token),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["items"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_edit_tag_and_unread_counts() {
+        let (app, db) = create_test_app(READER_CONFIG).await;
+        let feeds = seed_reader_items(&db).await;
+
+        let body = get_json(&app, "/reader/api/0/unread-count?output=json").await;
+        assert_eq!(unread(&body, READING_LIST), Some(6));
+        assert_eq!(unread(&body, "user/-/label/Tech"), Some(5));
+        assert_eq!(unread(&body, &format!("feed/{}", feeds[2].id)), Some(1));
+
+        let edit = "/reader/api/0/edit-tag";
+        let form = format!("i=1&i={}&a={}", long_item_id(4), READ);
+        post(&app, edit, &form).await;
+        post(&app, edit, "i=2&a=user/-/state/com.google/starred&T=token").await;
+
+        let body = get_json(&app, "/reader/api/0/unread-count").await;
+        assert_eq!(unread(&body, READING_LIST), Some(4));
+        assert_eq!(unread(&body, "user/-/label/Tech"), Some(3));
+        assert_eq!(unread(&body, &format!("feed/{}", feeds[1].id)), Some(1));
+
+        let uri = "/reader/api/0/stream/items/ids?s=user/-/state/com.google/starred";
+        assert_eq!(item_ids(&get_json(&app, uri).await), [2]);
+        let uri = "/reader/api/0/stream/items/ids?xt=user/-/state/com.google/read";
+        assert_eq!(item_ids(&get_json(&app, uri).await), [6, 5, 3, 2]);
+        let uri = "/reader/api/0/stream/items/ids?s=user/-/state/com.google/read";
+        assert_eq!(item_ids(&get_json(&app, uri).await), [4, 1]);
+        let body = get_json(&app, "/reader/api/0/stream/items/contents?i=2").await;
+        assert_eq!(body["items"][0]["categories"][2], STARRED);
+
+        post(&app, edit, "i=1&r=user/-/state/com.google/read").await;
+        post(&app, edit, "i=4&a=user/-/state/com.google/kept-unread").await;
+        post(&app, edit, "i=2&r=user/-/state/com.google/starred").await;
+        assert!(db.get_saved_item_ids().await.unwrap().is_empty());
+        assert_eq!(db.get_unread_item_ids().await.unwrap().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_mark_all_as_read() {
+        let (app, db) = create_test_app(READER_CONFIG).await;
+        let feeds = seed_reader_items(&db).await;
+
+        // Only what the app had seen: published up to 15 hours ago
+        let ts = (Utc::now() - chrono::Duration::hours(15)).timestamp() * 1_000_000;
+        let form = format!("s=user/-/label/Tech&ts={}", ts);
+        post(&app, "/reader/api/0/mark-all-as-read", &form).await;
+        assert_eq!(db.get_unread_item_ids().await.unwrap(), [3, 5, 6]);
+
+        let form = format!("s=feed/{}", feeds[1].id);
+        post(&app, "/reader/api/0/mark-all-as-read", &form).await;
+        assert_eq!(db.get_unread_item_ids().await.unwrap(), [3, 6]);
+
+        post(&app, "/reader/api/0/mark-all-as-read", &format!("s={}", READING_LIST)).await;
+        assert!(db.get_unread_item_ids().await.unwrap().is_empty());
+
+        let uri = "/reader/api/0/mark-all-as-read";
+        let (status, _) = call(&app, "POST", uri, "s=bogus", Some(&app.token)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -3,7 +3,21 @@
 //! This crate provides an RSS feed aggregator with a web interface.
 //! It fetches feeds from multiple sources and displays them in a columnar layout.
 
+pub mod api;
 pub mod config;
 pub mod db;
+pub mod editor;
+pub mod export;
 pub mod fetcher;
+pub mod fever;
+pub mod filter;
+pub mod greader;
+pub mod opml;
+pub mod reload;
+pub mod rewrite;
 pub mod routes;
+pub mod stats;
+pub mod syndication;
+#[cfg(test)]
+mod test_support;
+pub mod tokens;
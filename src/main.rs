@@ -1,22 +1,168 @@
-mod config;
-mod db;
-mod fetcher;
-mod routes;
-
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use axum::{
     routing::{get, post},
     Router,
 };
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
 use tower_http::services::ServeDir;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::config::Config;
-use crate::db::Database;
-use crate::fetcher::{start_background_refresh, Fetcher};
-use crate::routes::AppState;
+use moar_news::api;
+use moar_news::config::{Config, FeedConfig};
+use moar_news::db::{Database, Feed};
+use moar_news::export::{self, ExportDocument};
+use moar_news::fetcher::{start_background_refresh, Fetcher};
+use moar_news::fever;
+use moar_news::greader;
+use moar_news::opml;
+use moar_news::reload::ConfigReloader;
+use moar_news::rewrite::TitleRewriter;
+use moar_news::routes::{self, AppState};
+use moar_news::stats::format_age;
+use moar_news::syndication;
+use moar_news::tokens::Signer;
+
+#[derive(Parser)]
+#[command(version, about = "A lightweight, self-hosted RSS feed aggregator")]
+struct Cli {
+    /// Feed configuration file
+    #[arg(long, global = true, env = "CONFIG_PATH", default_value = "feeds.toml")]
+    config: PathBuf,
+
+    /// SQLite connection URL or database file path [fallback env: DATABASE_PATH]
+    #[arg(long, global = true, env = "DATABASE_URL")]
+    database: Option<String>,
+
+    /// Server flags, for when no command is given; after `serve` otherwise
+    #[command(flatten)]
+    serve: ServeArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the web server (the default when no command is given)
+    Serve(ServeArgs),
+    /// Fetch every feed once and exit, e.g. from cron
+    Fetch,
+    /// List feeds with their last fetch status
+    ListFeeds,
+    /// Check the configuration file and exit
+    ValidateConfig,
+    /// Fetch feeds that have title rules and print the titles they change, storing nothing
+    PreviewTitles {
+        /// Only this feed, by name, whether or not it has title rules
+        #[arg(long)]
+        feed: Option<String>,
+    },
+    /// Write a consistent snapshot of the live database to a new file
+    Backup {
+        /// Destination file; must not already exist
+        path: PathBuf,
+    },
+    /// Export feeds and items as a versioned JSON document
+    Export {
+        /// Output file (defaults to stdout)
+        path: Option<PathBuf>,
+    },
+    /// Import a JSON export into a fresh database
+    Import {
+        /// Export document to read
+        path: PathBuf,
+    },
+    /// Write the feed list as OPML
+    ExportOpml {
+        /// Output file (defaults to stdout)
+        path: Option<PathBuf>,
+    },
+    /// Read an OPML file and print `[[feeds]]` entries for feeds not yet configured
+    ImportOpml {
+        /// OPML file to read
+        path: PathBuf,
+        /// Add the feeds to the database instead of printing config entries
+        #[arg(long)]
+        merge: bool,
+    },
+    /// Run SQLite's integrity check and report any problems
+    IntegrityCheck,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// Address and port to listen on
+    #[arg(long, env = "BIND_ADDRESS", default_value = "0.0.0.0:3000")]
+    bind: String,
+
+    /// Directory served under /static
+    #[arg(long, env = "STATIC_DIR", default_value = "static")]
+    static_dir: PathBuf,
+
+    /// Bearer token for admin endpoints; they are disabled when unset
+    #[arg(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Serve under this URL path, e.g. /news, instead of at the root
+    #[arg(long, env = "BASE_PATH", default_value = "", value_parser = parse_base_path)]
+    base_path: String,
+
+    /// Honor X-Forwarded-Prefix and X-Forwarded-Proto; only behind a proxy that sets them
+    #[arg(long, env = "TRUST_FORWARDED_HEADERS")]
+    trust_forwarded_headers: bool,
+}
+
+/// Parse the command line. Server flags given before a command are refused
+/// rather than ignored; they belong after `serve`.
+fn parse_cli(
+    args: impl IntoIterator<Item = impl Into<OsString> + Clone>,
+) -> Result<Cli, clap::Error> {
+    let mut command = Cli::command();
+    let matches = command.try_get_matches_from_mut(args)?;
+    if let Some((name, _)) = matches.subcommand() {
+        let misplaced = command.get_arguments().find(|arg| {
+            !arg.is_global_set()
+                && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+        });
+        if let Some(arg) = misplaced {
+            let flag = format!("--{}", arg.get_long().unwrap_or_default());
+            let message = match name {
+                "serve" => format!("`{}` goes after `serve`", flag),
+                _ => format!("`{}` only applies to `serve`, not `{}`", flag, name),
+            };
+            return Err(command.error(ErrorKind::ArgumentConflict, message));
+        }
+    }
+    Cli::from_arg_matches(&matches)
+}
+
+fn parse_base_path(path: &str) -> Result<String, String> {
+    routes::normalize_base_path(path)
+        .ok_or_else(|| "expected a path like /news, of letters, digits and -._~".to_string())
+}
+
+/// Resolve the database to open: `--database`/`DATABASE_URL`, then `DATABASE_PATH`,
+/// then `moar_news.db` in the working directory. Plain paths become `sqlite:` URLs.
+fn database_url(database: Option<&str>, database_path: Option<&str>) -> String {
+    let database = database
+        .or(database_path)
+        .filter(|d| !d.is_empty())
+        .unwrap_or("moar_news.db");
+
+    if database.starts_with("sqlite:") {
+        database.to_string()
+    } else {
+        format!("sqlite:{}?mode=rwc", database)
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -29,51 +175,422 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Load configuration
-    let config = Config::load("feeds.toml")?;
+    let cli = parse_cli(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    let database_path = std::env::var("DATABASE_PATH").ok();
+    let url = database_url(cli.database.as_deref(), database_path.as_deref());
+    let config = cli.config;
+
+    match cli.command.unwrap_or(Command::Serve(cli.serve)) {
+        Command::Serve(args) => serve(&config, &url, args).await,
+        Command::Fetch => fetch(&config, &url).await,
+        Command::ListFeeds => list_feeds(&config, &url).await,
+        Command::ValidateConfig => validate_config(&config),
+        Command::PreviewTitles { feed } => preview_titles(&config, &url, feed).await,
+        Command::Backup { path } => backup(&url, path).await,
+        Command::Export { path } => export_db(&url, path).await,
+        Command::Import { path } => import_db(&url, path).await,
+        Command::ExportOpml { path } => export_opml(&url, path).await,
+        Command::ImportOpml { path, merge } => import_opml(&config, &url, path, merge).await,
+        Command::IntegrityCheck => integrity_check(&url).await,
+    }
+}
+
+/// Load the configuration and open, initialize and sync the database
+async fn open(config_path: &Path, url: &str) -> anyhow::Result<(Config, Database)> {
+    let config = Config::load(config_path)?;
     info!("Loaded {} feeds from configuration", config.feeds.len());
+    for (source, count) in feeds_per_file(&config) {
+        info!("  {}: {} feeds", source.display(), count);
+    }
 
-    // Initialize database
-    let database_url = std::env::var("DATABASE_URL")
-        .unwrap_or_else(|_| "sqlite:moar_news.db?mode=rwc".to_string());
-    let db = Database::new(&database_url).await?;
+    let db = Database::connect(url, &config.database).await?;
+    info!("SQLite settings: {}", db.effective_pragmas().await?);
     db.initialize().await?;
-    db.sync_feeds(&config.feeds).await?;
+    // Feeds removed while the server was stopped keep their items, as on reload
+    let removed = db.sync_feeds(&config.all_feeds()).await?;
+    if removed > 0 {
+        info!("{} feeds left the configuration; their items are kept", removed);
+    }
     info!("Database initialized");
 
+    Ok((config, db))
+}
+
+async fn serve(config_path: &Path, url: &str, args: ServeArgs) -> anyhow::Result<()> {
+    let (config, db) = open(config_path, url).await?;
     let db = Arc::new(db);
 
     // Create fetcher
     let fetcher = Arc::new(Fetcher::new(db.clone()));
+    fetcher.configure_feeds(&config).await?;
+
+    // Watch the config file and SIGHUP for edits
+    let reloader = Arc::new(ConfigReloader::new(
+        config_path,
+        config,
+        db.clone(),
+        fetcher.clone(),
+    ));
+    tokio::spawn(reloader.clone().watch_file());
+    tokio::spawn(reloader.clone().watch_sighup());
 
     // Start background refresh task
     let bg_fetcher = fetcher.clone();
-    let refresh_interval = config.refresh_interval;
+    let refresh_interval = reloader.refresh_interval();
     tokio::spawn(async move {
         start_background_refresh(bg_fetcher, refresh_interval).await;
     });
 
+    let admin_token = args.admin_token.filter(|t| !t.is_empty());
+    if admin_token.is_none() {
+        info!("ADMIN_TOKEN not set, admin endpoints are disabled");
+    }
+
     // Create app state
     let state = Arc::new(AppState {
+        signer: Arc::new(Signer::load(&db).await?),
         db: db.clone(),
         fetcher: fetcher.clone(),
+        reloader,
+        admin_token,
+        base_path: args.base_path.clone(),
+        trust_forwarded_headers: args.trust_forwarded_headers,
     });
 
     // Build router
     let app = Router::new()
         .route("/", get(routes::index))
+        .route("/group/:slug", get(routes::group))
         .route("/feed/:id/more", get(routes::feed_more))
+        .route("/item/:id/revisions", get(routes::item_revisions))
+        .route("/stats", get(routes::stats))
+        .route("/feeds.opml", get(routes::feeds_opml))
         .route("/refresh", post(routes::refresh))
         .route("/refresh/status", get(routes::refresh_status))
+        .route("/admin/reload", post(routes::admin_reload))
+        .route("/admin/login", post(routes::admin_login))
+        .route("/admin/logout", post(routes::admin_logout))
+        .route("/admin/feeds", get(routes::admin_feeds))
+        .route("/admin/feeds/test", post(routes::admin_feed_test))
+        .route("/admin/feeds/:action", post(routes::admin_feed_action))
         .route("/health", get(routes::health))
-        .nest_service("/static", ServeDir::new("static"))
-        .with_state(state);
+        .nest("/api/v1", api::router())
+        .merge(syndication::router())
+        .merge(fever::router())
+        .merge(greader::router())
+        .nest_service("/static", ServeDir::new(&args.static_dir));
+    let app = if args.base_path.is_empty() {
+        app
+    } else {
+        info!("Serving under {}/", args.base_path);
+        // Nesting only matches the bare prefix; the dashboard is linked with a trailing slash
+        Router::new()
+            .nest(&args.base_path, app)
+            .route(&format!("{}/", args.base_path), get(routes::index))
+    };
+    let app = app.with_state(state);
 
     // Start server
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
-    info!("Server starting on http://localhost:3000");
+    let listener = tokio::net::TcpListener::bind(&args.bind)
+        .await
+        .with_context(|| format!("Failed to bind {}", args.bind))?;
+    info!("Server starting on http://{}", listener.local_addr()?);
 
     axum::serve(listener, app).await?;
 
     Ok(())
 }
+
+async fn fetch(config_path: &Path, url: &str) -> anyhow::Result<()> {
+    let (config, db) = open(config_path, url).await?;
+    let db = Arc::new(db);
+
+    let fetcher = Fetcher::new(db.clone());
+    fetcher.configure_feeds(&config).await?;
+    fetcher.refresh_all_feeds().await?;
+
+    let feeds = db.get_all_feeds().await?;
+    let failed = feeds.iter().filter(|f| f.last_error.is_some()).count();
+    println!("Fetched {} feeds, {} failed", feeds.len(), failed);
+    Ok(())
+}
+
+async fn list_feeds(config_path: &Path, url: &str) -> anyhow::Result<()> {
+    let config = Config::load(config_path)?;
+    let db = Database::connect(url, &config.database).await?;
+    db.initialize().await?;
+    let known = db.get_all_feeds().await?;
+
+    for feed_config in &config.feeds {
+        let status = match known.iter().find(|f| f.url == feed_config.url) {
+            Some(feed) => fetch_status(feed),
+            None => "not yet added".to_string(),
+        };
+        println!("{}\t{}\t{}", feed_config.name, feed_config.url, status);
+    }
+
+    // Feeds that stayed in the database after leaving the config
+    for feed in known
+        .iter()
+        .filter(|f| !config.feeds.iter().any(|c| c.url == f.url))
+    {
+        println!("{}\t{}\t{} (not in config)", feed.name, feed.url, fetch_status(feed));
+    }
+    Ok(())
+}
+
+/// How many feeds each configuration file defined, in load order
+fn feeds_per_file(config: &Config) -> Vec<(&Path, usize)> {
+    let mut counts: Vec<(&Path, usize)> = Vec::new();
+    for source in config.feeds.iter().filter_map(|f| f.source.as_deref()) {
+        match counts.iter_mut().find(|(path, _)| *path == source) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((source, 1)),
+        }
+    }
+    counts
+}
+
+fn fetch_status(feed: &Feed) -> String {
+    let age = feed
+        .last_fetched
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| format_age(Utc::now() - t.with_timezone(&Utc)));
+
+    match (age, &feed.last_error) {
+        (None, _) => "never fetched".to_string(),
+        (Some(age), None) => format!("ok {} ago", age),
+        (Some(age), Some(error)) => format!("failed {} ago: {}", age, error),
+    }
+}
+
+fn validate_config(config_path: &Path) -> anyhow::Result<()> {
+    let config = match Config::load(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "{}: {} feeds, refreshing every {} minutes",
+        config_path.display(),
+        config.feeds.len(),
+        config.refresh_interval
+    );
+    for (source, count) in feeds_per_file(&config) {
+        println!("  {}: {} feeds", source.display(), count);
+    }
+    if !config.disabled_feeds.is_empty() {
+        println!("  {} disabled feeds", config.disabled_feeds.len());
+    }
+    Ok(())
+}
+
+async fn preview_titles(config_path: &Path, url: &str, name: Option<String>) -> anyhow::Result<()> {
+    let config = Config::load(config_path)?;
+    let feeds: Vec<&FeedConfig> = match &name {
+        Some(name) => vec![config
+            .feeds
+            .iter()
+            .find(|f| &f.name == name)
+            .with_context(|| format!("No feed named `{}` in the configuration", name))?],
+        None => config
+            .feeds
+            .iter()
+            .filter(|f| TitleRewriter::compile(f).is_ok_and(|r| !r.is_empty()))
+            .collect(),
+    };
+    if feeds.is_empty() {
+        println!("No feeds have title rules");
+        return Ok(());
+    }
+
+    let fetcher = Fetcher::new(Arc::new(Database::new(url).await?));
+    fetcher.configure_feeds(&config).await?;
+    for feed in feeds {
+        let titles = match fetcher.preview_titles(&feed.url, &feed.name).await {
+            Ok(titles) => titles,
+            Err(e) => {
+                println!("{}: {}", feed.name, e);
+                continue;
+            }
+        };
+        let changed: Vec<_> = titles.iter().filter(|(before, after)| before != after).collect();
+        println!("{}: {} of {} titles change", feed.name, changed.len(), titles.len());
+        for (before, after) in changed {
+            println!("  - {:?}\n  + {:?}", before, after);
+        }
+    }
+    Ok(())
+}
+
+async fn backup(url: &str, path: PathBuf) -> anyhow::Result<()> {
+    let db = Database::new(url).await?;
+    db.backup_to(&path).await?;
+    println!("Backup written to {}", path.display());
+    Ok(())
+}
+
+async fn export_db(url: &str, path: Option<PathBuf>) -> anyhow::Result<()> {
+    let db = Database::new(url).await?;
+    db.initialize().await?;
+    let doc = export::export(&db).await?;
+    let json = serde_json::to_string_pretty(&doc)?;
+
+    match path {
+        Some(path) => {
+            std::fs::write(&path, json)?;
+            info!("Exported {} feeds to {}", doc.feeds.len(), path.display());
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+async fn import_db(url: &str, path: PathBuf) -> anyhow::Result<()> {
+    let content = std::fs::read_to_string(&path)?;
+    let doc: ExportDocument = serde_json::from_str(&content)?;
+
+    let db = Database::new(url).await?;
+    db.initialize().await?;
+    export::import(&db, &doc).await?;
+
+    let items: usize = doc.feeds.iter().map(|f| f.items.len()).sum();
+    println!("Imported {} feeds and {} items", doc.feeds.len(), items);
+    Ok(())
+}
+
+async fn export_opml(url: &str, path: Option<PathBuf>) -> anyhow::Result<()> {
+    let db = Database::new(url).await?;
+    db.initialize().await?;
+    let feeds: Vec<Feed> =
+        db.get_all_feeds().await?.into_iter().map(Feed::with_public_url).collect();
+    let xml = opml::export(&feeds, "Moar News subscriptions");
+
+    match path {
+        Some(path) => {
+            std::fs::write(&path, xml)?;
+            info!("Exported {} feeds to {}", feeds.len(), path.display());
+        }
+        None => print!("{}", xml),
+    }
+    Ok(())
+}
+
+async fn import_opml(
+    config_path: &Path,
+    url: &str,
+    path: PathBuf,
+    merge: bool,
+) -> anyhow::Result<()> {
+    let content = std::fs::read_to_string(&path)?;
+    let feeds = opml::parse(&content)?;
+
+    if merge {
+        let db = Database::new(url).await?;
+        db.initialize().await?;
+        let added = db.merge_feeds(&feeds).await?;
+        println!("Added {} of {} feeds to the database", added, feeds.len());
+        return Ok(());
+    }
+
+    // Leave out feeds the current config already has, if there is one
+    let configured: Vec<String> = match Config::load(config_path) {
+        Ok(config) => config.feeds.into_iter().map(|f| f.url).collect(),
+        Err(_) => Vec::new(),
+    };
+    let new_feeds: Vec<_> = feeds
+        .into_iter()
+        .filter(|f| !configured.contains(&f.xml_url))
+        .collect();
+
+    print!("{}", opml::to_toml(&new_feeds));
+    eprintln!(
+        "{} new feeds; append them to {}",
+        new_feeds.len(),
+        config_path.display()
+    );
+    Ok(())
+}
+
+async fn integrity_check(url: &str) -> anyhow::Result<()> {
+    let db = Database::new(url).await?;
+    let problems = db.integrity_check().await?;
+
+    if problems.is_empty() {
+        println!("ok");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    anyhow::bail!("Integrity check found {} problem(s)", problems.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_database_url_resolution() {
+        assert_eq!(database_url(None, None), "sqlite:moar_news.db?mode=rwc");
+        assert_eq!(
+            database_url(None, Some("/data/moar_news.db")),
+            "sqlite:/data/moar_news.db?mode=rwc"
+        );
+        assert_eq!(
+            database_url(Some("sqlite::memory:"), Some("/data/moar_news.db")),
+            "sqlite::memory:"
+        );
+        assert_eq!(database_url(Some("news.db"), None), "sqlite:news.db?mode=rwc");
+    }
+
+    #[test]
+    fn test_serve_is_default_with_global_flags() {
+        let cli = Cli::try_parse_from(["moar-news", "--config", "/etc/feeds.toml"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.config, PathBuf::from("/etc/feeds.toml"));
+        assert_eq!(cli.serve.static_dir, PathBuf::from("static"));
+
+        let cli = Cli::try_parse_from(["moar-news", "serve", "--bind", "127.0.0.1:8080"]).unwrap();
+        match cli.command {
+            Some(Command::Serve(args)) => assert_eq!(args.bind, "127.0.0.1:8080"),
+            _ => panic!("expected serve"),
+        }
+    }
+
+    #[test]
+    fn test_serve_flags_rejected_before_a_command() {
+        let cli = parse_cli(["moar-news", "--bind", "127.0.0.1:8080"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.serve.bind, "127.0.0.1:8080");
+
+        let error = parse_cli(["moar-news", "--bind", "127.0.0.1:8080", "serve"]).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+        assert!(error.to_string().contains("`--bind` goes after `serve`"));
+        let error = parse_cli(["moar-news", "--bind", "x", "validate-config"]).err().unwrap();
+        assert!(error.to_string().contains("`--bind` only applies to `serve`"));
+
+        let cli = parse_cli(["moar-news", "--config", "a.toml", "validate-config"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::ValidateConfig)));
+        assert_eq!(cli.config, PathBuf::from("a.toml"));
+    }
+
+    #[test]
+    fn test_base_path_is_normalized() {
+        let cli = Cli::try_parse_from(["moar-news", "--base-path", "/news/"]).unwrap();
+        assert_eq!(cli.serve.base_path, "/news");
+        let cli = Cli::try_parse_from(["moar-news", "--base-path", "/"]).unwrap();
+        assert_eq!(cli.serve.base_path, "");
+        assert!(Cli::try_parse_from(["moar-news", "--base-path", "news"]).is_err());
+        assert!(Cli::try_parse_from(["moar-news", "--base-path", "/a\"b"]).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! OPML 2.0 subscription lists, for moving feeds to and from other readers.
+//!
+//! Feed groups map to top-level folder outlines. On import, nested folders are
+//! flattened into a single group name joined with " / ".
+
+use anyhow::Context;
+use chrono::Utc;
+use quick_xml::escape::{escape, unescape};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::db::Feed;
+
+/// A subscription read from an OPML file
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpmlFeed {
+    pub title: String,
+    pub xml_url: String,
+    pub html_url: Option<String>,
+    pub group: Option<String>,
+}
+
+enum Entry<'a> {
+    Feed(&'a Feed),
+    Group(&'a str, Vec<&'a Feed>),
+}
+
+/// Render feeds as an OPML document. Groups appear where their first feed does.
+pub fn export(feeds: &[Feed], title: &str) -> String {
+    let mut entries: Vec<Entry> = Vec::new();
+    for feed in feeds {
+        match feed.group_name.as_deref() {
+            None => entries.push(Entry::Feed(feed)),
+            Some(group) => {
+                let existing = entries.iter_mut().find_map(|entry| match entry {
+                    Entry::Group(name, feeds) if *name == group => Some(feeds),
+                    _ => None,
+                });
+                match existing {
+                    Some(feeds) => feeds.push(feed),
+                    None => entries.push(Entry::Group(group, vec![feed])),
+                }
+            }
+        }
+    }
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<opml version=\"2.0\">\n  <head>\n");
+    out.push_str(&format!("    <title>{}</title>\n", escape(title)));
+    out.push_str(&format!(
+        "    <dateCreated>{}</dateCreated>\n",
+        Utc::now().to_rfc2822()
+    ));
+    out.push_str("  </head>\n  <body>\n");
+
+    for entry in entries {
+        match entry {
+            Entry::Feed(feed) => push_feed(&mut out, feed, 4),
+            Entry::Group(name, feeds) => {
+                let name = escape(name);
+                out.push_str(&format!(
+                    "    <outline text=\"{}\" title=\"{}\">\n",
+                    name, name
+                ));
+                for feed in feeds {
+                    push_feed(&mut out, feed, 6);
+                }
+                out.push_str("    </outline>\n");
+            }
+        }
+    }
+
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+fn push_feed(out: &mut String, feed: &Feed, indent: usize) {
+    let name = escape(&feed.name);
+    out.push_str(&format!(
+        "{:indent$}<outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\"",
+        "",
+        name,
+        name,
+        escape(&feed.url),
+        indent = indent
+    ));
+    if let Some(homepage) = &feed.homepage_url {
+        out.push_str(&format!(" htmlUrl=\"{}\"", escape(homepage)));
+    }
+    out.push_str("/>\n");
+}
+
+/// Read the subscriptions from an OPML document, in document order.
+/// Outlines without an `xmlUrl` are treated as folders; repeated URLs are dropped.
+pub fn parse(xml: &str) -> anyhow::Result<Vec<OpmlFeed>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut feeds: Vec<OpmlFeed> = Vec::new();
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut seen_root = false;
+
+    loop {
+        let event = reader
+            .read_event()
+            .with_context(|| format!("Invalid OPML at byte {}", reader.buffer_position()))?;
+
+        match event {
+            Event::Start(e) | Event::Empty(e) if e.name().as_ref() == b"opml" => {
+                seen_root = true;
+            }
+            Event::Start(e) if e.name().as_ref() == b"outline" => {
+                // Feeds may themselves contain outlines; only folders name a group
+                match outline_feed(&e, &folders)? {
+                    Some(feed) => {
+                        push_unique(&mut feeds, feed);
+                        folders.push(None);
+                    }
+                    None => folders.push(Some(outline_text(&e)?.unwrap_or_default())),
+                }
+            }
+            Event::Empty(e) if e.name().as_ref() == b"outline" => {
+                if let Some(feed) = outline_feed(&e, &folders)? {
+                    push_unique(&mut feeds, feed);
+                }
+            }
+            Event::End(e) if e.name().as_ref() == b"outline" => {
+                folders.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !seen_root {
+        anyhow::bail!("Not an OPML document (no <opml> element)");
+    }
+    Ok(feeds)
+}
+
+fn push_unique(feeds: &mut Vec<OpmlFeed>, feed: OpmlFeed) {
+    if !feeds.iter().any(|f| f.xml_url == feed.xml_url) {
+        feeds.push(feed);
+    }
+}
+
+fn attribute(e: &BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    for attr in e.attributes() {
+        let attr = attr?;
+        if attr.key.as_ref().eq_ignore_ascii_case(name.as_bytes()) {
+            let value = unescape(std::str::from_utf8(&attr.value)?)?.trim().to_string();
+            return Ok(Some(value).filter(|v| !v.is_empty()));
+        }
+    }
+    Ok(None)
+}
+
+/// The outline's label: `title`, falling back to the required `text`
+fn outline_text(e: &BytesStart) -> anyhow::Result<Option<String>> {
+    Ok(attribute(e, "title")?.or(attribute(e, "text")?))
+}
+
+fn outline_feed(e: &BytesStart, folders: &[Option<String>]) -> anyhow::Result<Option<OpmlFeed>> {
+    let Some(xml_url) = attribute(e, "xmlUrl")? else {
+        return Ok(None);
+    };
+
+    let path: Vec<&str> = folders
+        .iter()
+        .flatten()
+        .map(String::as_str)
+        .filter(|f| !f.is_empty())
+        .collect();
+
+    Ok(Some(OpmlFeed {
+        title: outline_text(e)?.unwrap_or_else(|| xml_url.clone()),
+        html_url: attribute(e, "htmlUrl")?,
+        group: (!path.is_empty()).then(|| path.join(" / ")),
+        xml_url,
+    }))
+}
+
+/// Render subscriptions as `[[feeds]]` entries to paste into feeds.toml
+pub fn to_toml(feeds: &[OpmlFeed]) -> String {
+    let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+
+    let mut out = String::new();
+    for feed in feeds {
+        out.push_str("[[feeds]]\n");
+        out.push_str(&format!("name = {}\n", quote(&feed.title)));
+        out.push_str(&format!("url = {}\n", quote(&feed.xml_url)));
+        if let Some(group) = &feed.group {
+            out.push_str(&format!("group = {}\n", quote(group)));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="1.0">
+  <head><title>Subscriptions</title></head>
+  <body>
+    <outline text="Hacker News" type="rss" xmlUrl="https://news.ycombinator.com/rss"/>
+    <outline text="Tech">
+      <outline title="Ars &amp; Co" text="ars" type="rss"
+               xmlUrl="https://feeds.arstechnica.com/arstechnica/index"
+               htmlUrl="https://arstechnica.com"/>
+      <outline text="Apple">
+        <outline text="Daring Fireball" xmlurl="https://daringfireball.net/feeds/main"/>
+      </outline>
+    </outline>
+    <outline text="Duplicate" xmlUrl="https://news.ycombinator.com/rss"/>
+  </body>
+</opml>"#;
+
+    fn create_feed(name: &str, url: &str, group: Option<&str>) -> Feed {
+        Feed {
+            id: 1,
+            name: name.to_string(),
+            url: url.to_string(),
+            group_name: group.map(|g| g.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_maps_folders_to_groups() {
+        let feeds = parse(SAMPLE).unwrap();
+
+        assert_eq!(feeds.len(), 3);
+        assert_eq!(feeds[0].title, "Hacker News");
+        assert_eq!(feeds[0].group, None);
+        assert_eq!(feeds[1].title, "Ars & Co");
+        assert_eq!(feeds[1].group.as_deref(), Some("Tech"));
+        assert_eq!(feeds[1].html_url.as_deref(), Some("https://arstechnica.com"));
+        assert_eq!(feeds[2].xml_url, "https://daringfireball.net/feeds/main");
+        assert_eq!(feeds[2].group.as_deref(), Some("Tech / Apple"));
+    }
+
+    #[test]
+    fn test_parse_rejects_non_opml() {
+        assert!(parse("<rss><channel/></rss>").is_err());
+        assert!(parse("<opml><body><outline</body></opml>").is_err());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut hn = create_feed("HN <news>", "https://news.ycombinator.com/rss", None);
+        hn.homepage_url = Some("https://news.ycombinator.com/".to_string());
+        let feeds = vec![
+            hn,
+            create_feed("Ars", "https://ars.example.com/rss", Some("Tech")),
+            create_feed("NPR", "https://npr.example.com/rss", None),
+            create_feed("Verge", "https://verge.example.com/rss", Some("Tech")),
+        ];
+
+        let xml = export(&feeds, "Moar News");
+        assert!(xml.contains("<opml version=\"2.0\">"));
+        assert!(xml.contains("text=\"HN &lt;news&gt;\""));
+
+        let parsed = parse(&xml).unwrap();
+        let titles: Vec<&str> = parsed.iter().map(|f| f.title.as_str()).collect();
+        assert_eq!(titles, vec!["HN <news>", "Ars", "Verge", "NPR"]);
+        assert_eq!(
+            parsed[0].html_url.as_deref(),
+            Some("https://news.ycombinator.com/")
+        );
+        assert_eq!(parsed[2].group.as_deref(), Some("Tech"));
+    }
+
+    #[test]
+    fn test_to_toml_is_valid_config() {
+        let feeds = parse(SAMPLE).unwrap();
+
+        let config = Config::from_str(&to_toml(&feeds)).unwrap();
+
+        assert_eq!(config.feeds.len(), 3);
+        assert_eq!(config.feeds[1].name, "Ars & Co");
+        assert_eq!(config.feeds[2].group.as_deref(), Some("Tech / Apple"));
+    }
+}
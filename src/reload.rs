@@ -0,0 +1,504 @@
+//! Applying edits to feeds.toml while the server is running.
+//!
+//! A reload is triggered by the file changing on disk, by SIGHUP, or through
+//! the admin endpoint. The new file is parsed in full before anything is
+//! touched, so a broken edit leaves the previous configuration in force.
+
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use tokio::sync::{watch, Mutex, RwLock};
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::fetcher::Fetcher;
+use crate::filter::Keywords;
+
+/// How often the config file's modification time is checked
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a successful reload changed
+#[derive(Debug, PartialEq)]
+pub struct ReloadSummary {
+    pub feeds: usize,
+    pub added: usize,
+    pub removed: u64,
+    pub refresh_interval: u64,
+}
+
+impl fmt::Display for ReloadSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} feeds ({} added, {} removed), refreshing every {} minutes",
+            self.feeds, self.added, self.removed, self.refresh_interval
+        )
+    }
+}
+
+pub struct ConfigReloader {
+    path: PathBuf,
+    db: Arc<Database>,
+    fetcher: Arc<Fetcher>,
+    active: RwLock<Active>,
+    interval: watch::Sender<u64>,
+    /// Serializes reloads so two triggers can't interleave their database writes
+    reloading: Mutex<()>,
+}
+
+/// The configuration in force, with its keyword lists compiled once per load
+struct Active {
+    config: Arc<Config>,
+    keywords: Arc<Keywords>,
+}
+
+impl Active {
+    fn compile(config: Config) -> anyhow::Result<Self> {
+        let keywords = Keywords::compile(&config.keywords).context("Invalid keyword rule")?;
+        Ok(Self {
+            config: Arc::new(config),
+            keywords: Arc::new(keywords),
+        })
+    }
+}
+
+impl ConfigReloader {
+    /// Track `path`, whose contents were already loaded and applied as `config`
+    pub fn new(
+        path: impl Into<PathBuf>,
+        config: Config,
+        db: Arc<Database>,
+        fetcher: Arc<Fetcher>,
+    ) -> Self {
+        let (interval, _) = watch::channel(config.refresh_interval);
+        let active =
+            Active::compile(config).expect("keyword rules are checked when the config is loaded");
+        Self {
+            path: path.into(),
+            db,
+            fetcher,
+            active: RwLock::new(active),
+            interval,
+            reloading: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The configuration currently in force
+    pub async fn config(&self) -> Arc<Config> {
+        self.active.read().await.config.clone()
+    }
+
+    /// Highlight and mute lists of the configuration currently in force
+    pub async fn keywords(&self) -> Arc<Keywords> {
+        self.active.read().await.keywords.clone()
+    }
+
+    /// Refresh interval in minutes, updated whenever a reload changes it
+    pub fn refresh_interval(&self) -> watch::Receiver<u64> {
+        self.interval.subscribe()
+    }
+
+    /// Re-read the config file and apply it.
+    /// On error the previous configuration stays in force.
+    pub async fn reload(&self) -> anyhow::Result<ReloadSummary> {
+        let _guard = self.reloading.lock().await;
+        self.reload_locked().await
+    }
+
+    /// Rewrite `file`, the config file or one it includes, with `edit` and
+    /// apply the result. If the edited configuration is rejected, the file is
+    /// put back as it was and the previous configuration stays in force.
+    pub async fn edit_file(
+        &self,
+        file: &Path,
+        edit: impl FnOnce(&str) -> anyhow::Result<String>,
+    ) -> anyhow::Result<ReloadSummary> {
+        let _guard = self.reloading.lock().await;
+
+        let original = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let edited = edit(&original)?;
+        replace_file(file, &edited)?;
+
+        match self.reload_locked().await {
+            Ok(summary) => Ok(summary),
+            Err(e) => {
+                if let Err(restore) = replace_file(file, &original) {
+                    error!("Failed to restore {}: {:#}", file.display(), restore);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn reload_locked(&self) -> anyhow::Result<ReloadSummary> {
+        let new = Active::compile(Config::load(&self.path)?)?;
+        let new_config = new.config.clone();
+        let old_config = self.config().await;
+
+        let added = new_config
+            .feeds
+            .iter()
+            .filter(|new| !old_config.feeds.iter().any(|old| old.url == new.url))
+            .count();
+
+        // Feeds removed from the file keep their items, as when it is edited
+        // while the server is stopped
+        let removed = self.db.sync_feeds(&new_config.all_feeds()).await?;
+        self.fetcher.configure_feeds(&new_config).await?;
+
+        let summary = ReloadSummary {
+            feeds: new_config.feeds.len(),
+            added,
+            removed,
+            refresh_interval: new_config.refresh_interval,
+        };
+
+        self.interval.send_if_modified(|interval| {
+            let changed = *interval != new_config.refresh_interval;
+            *interval = new_config.refresh_interval;
+            changed
+        });
+        *self.active.write().await = new;
+
+        if added > 0 {
+            let fetcher = self.fetcher.clone();
+            tokio::spawn(async move {
+                if let Err(e) = fetcher.refresh_all_feeds().await {
+                    error!("Feed refresh after reload failed: {}", e);
+                }
+            });
+        }
+
+        Ok(summary)
+    }
+
+    /// Reload and log the outcome, for triggers that have nobody to report to
+    async fn reload_and_log(&self, trigger: &str) {
+        match self.reload().await {
+            Ok(summary) => info!("Reloaded {} ({}): {}", self.path.display(), trigger, summary),
+            Err(e) => error!(
+                "Rejected {} ({}), keeping previous configuration: {:#}",
+                self.path.display(),
+                trigger,
+                e
+            ),
+        }
+    }
+
+    /// Poll the config file and the files it includes, and reload whenever one
+    /// is changed, added or removed
+    pub async fn watch_file(self: Arc<Self>) {
+        let mut last_seen = self.file_times().await;
+
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+
+            let current = self.file_times().await;
+            if current.first().is_some_and(|(_, time)| time.is_some()) && current != last_seen {
+                last_seen = current;
+                self.reload_and_log("file changed").await;
+            }
+        }
+    }
+
+    /// Modification times of the config file, then of each included file
+    async fn file_times(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let mut files = vec![self.path.clone()];
+        if let Ok(included) = self.config().await.include_files(&self.path) {
+            files.extend(included);
+        }
+        files
+            .into_iter()
+            .map(|file| {
+                let time = modified(&file);
+                (file, time)
+            })
+            .collect()
+    }
+
+    /// Reload whenever the process receives SIGHUP
+    #[cfg(unix)]
+    pub async fn watch_sighup(self: Arc<Self>) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                warn!("Could not listen for SIGHUP: {}", e);
+                return;
+            }
+        };
+
+        while hangups.recv().await.is_some() {
+            self.reload_and_log("SIGHUP").await;
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn watch_sighup(self: Arc<Self>) {}
+}
+
+/// Write `content` to a temporary file next to `file` and rename it over
+/// `file`, so a crash or a full disk leaves either the old or the new version
+fn replace_file(file: &Path, content: &str) -> anyhow::Result<()> {
+    // Replace the file a symlink points to, not the link
+    let file = std::fs::canonicalize(file)
+        .with_context(|| format!("Failed to resolve {}", file.display()))?;
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let temp = file.with_file_name(format!(".{}.tmp", name));
+
+    let write = || -> std::io::Result<()> {
+        let mut out = std::fs::File::create(&temp)?;
+        out.write_all(content.as_bytes())?;
+        out.sync_all()?;
+        out.set_permissions(std::fs::metadata(&file)?.permissions())?;
+        std::fs::rename(&temp, &file)
+    };
+    write().map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        anyhow::Error::new(e).context(format!("Failed to write {}", file.display()))
+    })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    const INITIAL: &str = r#"
+        refresh_interval = 15
+
+        [[feeds]]
+        name = "Feed 1"
+        url = "https://feed1.com/rss"
+
+        [[feeds]]
+        name = "Feed 2"
+        url = "https://feed2.com/rss"
+    "#;
+
+    async fn create_reloader() -> (ConfigReloader, NamedTempFile, Arc<Database>) {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(INITIAL.as_bytes()).unwrap();
+
+        let config = Config::load(file.path()).unwrap();
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.initialize().await.unwrap();
+        db.sync_feeds(&config.feeds).await.unwrap();
+        let db = Arc::new(db);
+        let fetcher = Arc::new(Fetcher::new(db.clone()));
+
+        let reloader = ConfigReloader::new(file.path(), config, db.clone(), fetcher);
+        (reloader, file, db)
+    }
+
+    fn rewrite(file: &NamedTempFile, content: &str) {
+        std::fs::write(file.path(), content).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_applies_feed_changes() {
+        let (reloader, file, db) = create_reloader().await;
+        rewrite(
+            &file,
+            r#"
+            [[feeds]]
+            name = "Feed 2 renamed"
+            url = "https://feed2.com/rss"
+            "#,
+        );
+
+        let summary = reloader.reload().await.unwrap();
+
+        assert_eq!(summary.feeds, 1);
+        assert_eq!(summary.added, 0);
+        assert_eq!(summary.removed, 1);
+        let feeds = db.get_all_feeds().await.unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].name, "Feed 2 renamed");
+    }
+
+    #[tokio::test]
+    async fn test_removed_feed_keeps_its_items() {
+        let (reloader, file, db) = create_reloader().await;
+        let feed_id = db.get_all_feeds().await.unwrap()[0].id;
+        db.upsert_item(feed_id, "a", "Kept", "https://feed1.com/a", None, None, None)
+            .await
+            .unwrap();
+        let without_feed_1 = "[[feeds]]\nname = \"Feed 2\"\nurl = \"https://feed2.com/rss\"\n";
+        rewrite(&file, without_feed_1);
+
+        assert_eq!(reloader.reload().await.unwrap().removed, 1);
+        assert_eq!(db.get_disabled_feeds().await.unwrap()[0].id, feed_id);
+        assert_eq!(db.get_total_item_count().await.unwrap(), 0);
+
+        // Putting the feed back brings its items back
+        rewrite(&file, INITIAL);
+        let summary = reloader.reload().await.unwrap();
+        assert_eq!((summary.added, summary.removed), (1, 0));
+        assert_eq!(db.get_items_for_feed(feed_id, 10, None).await.unwrap().len(), 1);
+
+        // Startup applies the same rule to edits made while the server was stopped
+        let config = Config::from_str(without_feed_1).unwrap();
+        assert_eq!(db.sync_feeds(&config.feeds).await.unwrap(), 1);
+        assert!(db.get_feed(feed_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reload_reads_included_files() {
+        let (reloader, file, db) = create_reloader().await;
+        let dir = file.path().parent().unwrap();
+        let included = tempfile::Builder::new()
+            .suffix(".toml")
+            .tempfile_in(dir)
+            .unwrap();
+        std::fs::write(
+            included.path(),
+            "[[feeds]]\nname = \"Feed 2\"\nurl = \"https://feed2.com/rss\"\n",
+        )
+        .unwrap();
+        let name = included.path().file_name().unwrap().to_str().unwrap();
+        rewrite(
+            &file,
+            &format!(
+                "include = [\"{}\"]\n\
+                 [[feeds]]\nname = \"Feed 1\"\nurl = \"https://feed1.com/rss\"\n",
+                name
+            ),
+        );
+
+        let summary = reloader.reload().await.unwrap();
+
+        assert_eq!(summary.feeds, 2);
+        assert_eq!(summary.removed, 0);
+        assert_eq!(db.get_all_feeds().await.unwrap().len(), 2);
+        let times = reloader.file_times().await;
+        assert_eq!(times.len(), 2);
+        assert_eq!(times[1].0, included.path());
+    }
+
+    #[tokio::test]
+    async fn test_reload_publishes_new_interval() {
+        let (reloader, file, _db) = create_reloader().await;
+        let mut interval = reloader.refresh_interval();
+        rewrite(&file, &INITIAL.replace("refresh_interval = 15", "refresh_interval = 5"));
+
+        reloader.reload().await.unwrap();
+
+        assert!(interval.has_changed().unwrap());
+        assert_eq!(*interval.borrow_and_update(), 5);
+        assert_eq!(reloader.config().await.refresh_interval, 5);
+    }
+
+    #[tokio::test]
+    async fn test_reload_recompiles_keywords() {
+        let (reloader, file, _db) = create_reloader().await;
+        assert!(!reloader.keywords().await.emphasis("Rust 2.0").highlighted);
+        rewrite(
+            &file,
+            &format!("{}\n[keywords]\nhighlight = [{{ keyword = \"rust\" }}]\n", INITIAL),
+        );
+
+        reloader.reload().await.unwrap();
+
+        assert!(reloader.keywords().await.emphasis("Rust 2.0").highlighted);
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_interval_does_not_notify() {
+        let (reloader, _file, _db) = create_reloader().await;
+        let interval = reloader.refresh_interval();
+
+        reloader.reload().await.unwrap();
+
+        assert!(!interval.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_edit_keeps_previous_config() {
+        let (reloader, file, db) = create_reloader().await;
+        rewrite(&file, "refresh_interval = \"soon\"\n[[feeds]]\nname = \"Broken\"");
+
+        assert!(reloader.reload().await.is_err());
+
+        assert_eq!(reloader.config().await.feeds.len(), 2);
+        assert_eq!(db.get_all_feeds().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_edit_file_applies_edit() {
+        let (reloader, file, db) = create_reloader().await;
+
+        let summary = reloader
+            .edit_file(file.path(), |content| Ok(content.replace("Feed 1", "First")))
+            .await
+            .unwrap();
+
+        assert_eq!(summary.feeds, 2);
+        assert!(std::fs::read_to_string(file.path()).unwrap().contains("\"First\""));
+        assert_eq!(db.get_all_feeds().await.unwrap()[0].name, "First");
+        // Written through a temporary file that was renamed into place
+        let name = file.path().file_name().unwrap().to_string_lossy();
+        assert!(!file.path().with_file_name(format!(".{}.tmp", name)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_edit_restores_file() {
+        let (reloader, file, db) = create_reloader().await;
+
+        let result = reloader
+            .edit_file(file.path(), |content| Ok(content.replace("feed1.com", "feed2.com")))
+            .await;
+
+        assert!(result.unwrap_err().to_string().contains("duplicate feed URL"));
+        assert_eq!(std::fs::read_to_string(file.path()).unwrap(), INITIAL);
+        assert_eq!(db.get_all_feeds().await.unwrap()[0].url, "https://feed1.com/rss");
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_feeds_not_from_config() {
+        let (reloader, _file, db) = create_reloader().await;
+        db.merge_feeds(&[crate::opml::OpmlFeed {
+            title: "Imported".to_string(),
+            xml_url: "https://imported.com/rss".to_string(),
+            html_url: None,
+            group: None,
+        }])
+        .await
+        .unwrap();
+
+        let summary = reloader.reload().await.unwrap();
+
+        assert_eq!(summary.removed, 0);
+        assert_eq!(db.get_all_feeds().await.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_summary_display() {
+        let summary = ReloadSummary {
+            feeds: 3,
+            added: 1,
+            removed: 0,
+            refresh_interval: 15,
+        };
+        assert_eq!(
+            summary.to_string(),
+            "3 feeds (1 added, 0 removed), refreshing every 15 minutes"
+        );
+    }
+}
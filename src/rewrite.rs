@@ -0,0 +1,305 @@
+//! Clean-up of feed entries before they are stored.
+//!
+//! Per-feed title rules trim whitespace, strip site-name suffixes and apply
+//! regex substitutions, so the dashboard shows what the feed meant to say.
+//! Links of every feed lose tracking parameters and can be moved to https or
+//! to another host, per domain.
+
+use regex::Regex;
+use reqwest::Url;
+
+use crate::config::{FeedConfig, LinkRewrite, LinksConfig};
+
+/// Characters that separate a title from a trailing site name, as in "Story | Site"
+const SUFFIX_SEPARATORS: &[char] = &['|', '-', '–', '—', '·', '•', ':', '»'];
+
+/// Compiled title rules for one feed
+#[derive(Default)]
+pub struct TitleRewriter {
+    collapse_whitespace: bool,
+    strip_site_suffix: bool,
+    rewrites: Vec<(Regex, String)>,
+}
+
+impl TitleRewriter {
+    pub fn compile(feed: &FeedConfig) -> Result<Self, regex::Error> {
+        Ok(Self {
+            collapse_whitespace: feed.collapse_whitespace,
+            strip_site_suffix: feed.strip_site_suffix,
+            rewrites: feed
+                .title_rewrites
+                .iter()
+                .map(|rewrite| Ok((Regex::new(&rewrite.regex)?, rewrite.replace.clone())))
+                .collect::<Result<_, regex::Error>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.collapse_whitespace && !self.strip_site_suffix && self.rewrites.is_empty()
+    }
+
+    /// Rewrite `title`: collapse whitespace, then strip a suffix naming one of
+    /// `site_names`, then apply the substitutions in order. A title rewritten to
+    /// nothing is kept as it was.
+    pub fn apply(&self, title: &str, site_names: &[&str]) -> String {
+        let mut rewritten = if self.collapse_whitespace {
+            title.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            title.to_string()
+        };
+
+        if self.strip_site_suffix {
+            if let Some(stripped) = site_names
+                .iter()
+                .find_map(|name| strip_suffix(&rewritten, name))
+            {
+                rewritten = stripped.to_string();
+            }
+        }
+
+        for (regex, replace) in &self.rewrites {
+            rewritten = regex.replace_all(&rewritten, replace.as_str()).into_owned();
+        }
+
+        if rewritten.trim().is_empty() {
+            title.to_string()
+        } else {
+            rewritten
+        }
+    }
+}
+
+/// `title` without a trailing separator and `site`, compared case-insensitively,
+/// or `None` if it doesn't end that way
+fn strip_suffix<'a>(title: &'a str, site: &str) -> Option<&'a str> {
+    let site = site.trim();
+    if site.is_empty() || title.len() <= site.len() {
+        return None;
+    }
+    let split = title.len() - site.len();
+    if !title.is_char_boundary(split) || !title[split..].eq_ignore_ascii_case(site) {
+        return None;
+    }
+
+    let rest = title[..split].trim_end();
+    let stripped = rest.strip_suffix(SUFFIX_SEPARATORS)?.trim_end();
+    // "Site" alone, or a separator hugging a word as in "Spider-Site", is not a suffix
+    if stripped.is_empty() || rest.len() == split {
+        return None;
+    }
+    Some(stripped)
+}
+
+/// Compiled `[links]` settings
+#[derive(Default)]
+pub struct LinkCanonicalizer {
+    /// Lowercased parameter names, and whether they end in `*`
+    strip_params: Vec<(String, bool)>,
+    rewrites: Vec<LinkRewrite>,
+}
+
+impl LinkCanonicalizer {
+    pub fn compile(config: &LinksConfig) -> Self {
+        Self {
+            strip_params: config
+                .strip_params
+                .iter()
+                .map(|param| match param.strip_suffix('*') {
+                    Some(prefix) => (prefix.to_lowercase(), true),
+                    None => (param.to_lowercase(), false),
+                })
+                .collect(),
+            rewrites: config
+                .rewrites
+                .iter()
+                .map(|rewrite| LinkRewrite {
+                    domain: rewrite.domain.to_lowercase(),
+                    ..rewrite.clone()
+                })
+                .collect(),
+        }
+    }
+
+    fn strips(&self, param: &str) -> bool {
+        let param = param.to_lowercase();
+        self.strip_params.iter().any(|(name, prefix)| {
+            if *prefix {
+                param.starts_with(name.as_str())
+            } else {
+                param == *name
+            }
+        })
+    }
+
+    /// The canonical form of `link`, or `None` if no setting changes it.
+    /// Links that don't parse as URLs are left alone.
+    pub fn apply(&self, link: &str) -> Option<String> {
+        let mut url = Url::parse(link).ok()?;
+        let mut changed = false;
+
+        if url.query().is_some() && !self.strip_params.is_empty() {
+            let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+            let kept: Vec<&(String, String)> =
+                pairs.iter().filter(|(name, _)| !self.strips(name)).collect();
+            // Untouched queries keep their original encoding
+            if kept.len() < pairs.len() {
+                if kept.is_empty() {
+                    url.set_query(None);
+                } else {
+                    url.query_pairs_mut().clear().extend_pairs(kept);
+                }
+                changed = true;
+            }
+        }
+
+        let host = url.host_str().map(str::to_lowercase).unwrap_or_default();
+        let rewrite = self.rewrites.iter().find(|rewrite| {
+            host == rewrite.domain || host.ends_with(&format!(".{}", rewrite.domain))
+        });
+        if let Some(rewrite) = rewrite {
+            if rewrite.https && url.scheme() == "http" && url.set_scheme("https").is_ok() {
+                changed = true;
+            }
+            if let Some(target) = &rewrite.host {
+                let (name, port) = match target.rsplit_once(':') {
+                    Some((name, port)) => (name, port.parse().ok()),
+                    None => (target.as_str(), None),
+                };
+                if url.set_host(Some(name)).is_ok() && url.set_port(port).is_ok() {
+                    changed = true;
+                }
+            }
+        }
+
+        changed.then(|| url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn create_rewriter(rules: &str) -> TitleRewriter {
+        let content = format!(
+            "[[feeds]]\nname = \"Test\"\nurl = \"https://test.com/rss\"\n{}",
+            rules
+        );
+        let config = Config::from_str(&content).unwrap();
+        TitleRewriter::compile(&config.feeds[0]).unwrap()
+    }
+
+    #[test]
+    fn test_no_rules_leave_titles_alone() {
+        let rewriter = create_rewriter("");
+
+        assert!(rewriter.is_empty());
+        assert_eq!(rewriter.apply("  Spaced\n out  ", &["Test"]), "  Spaced\n out  ");
+    }
+
+    #[test]
+    fn test_collapse_whitespace() {
+        let rewriter = create_rewriter("collapse_whitespace = true");
+
+        assert_eq!(rewriter.apply("\n  Line one\n\t line two  \n", &[]), "Line one line two");
+    }
+
+    #[test]
+    fn test_strip_site_suffix() {
+        let rewriter = create_rewriter("strip_site_suffix = true");
+        let sites = ["Ars Technica", "Test"];
+
+        assert_eq!(rewriter.apply("New chips | Ars Technica", &sites), "New chips");
+        assert_eq!(rewriter.apply("New chips — ARS TECHNICA", &sites), "New chips");
+        assert_eq!(rewriter.apply("Unit test - Test", &sites), "Unit test");
+        // Not a separated suffix
+        assert_eq!(rewriter.apply("Spider-Test", &sites), "Spider-Test");
+        assert_eq!(rewriter.apply("Ars Technica", &sites), "Ars Technica");
+        assert_eq!(rewriter.apply("Notes on Ars Technica", &sites), "Notes on Ars Technica");
+    }
+
+    #[test]
+    fn test_rewrites_apply_in_order() {
+        let rewriter = create_rewriter(
+            r#"
+            collapse_whitespace = true
+            title_rewrites = [
+                { regex = "^★\\s*" },
+                { regex = "(?i)^\\[video\\]\\s*(.*)", replace = "$1 (video)" },
+                { regex = "\\(video\\)$", replace = "🎥" },
+            ]
+            "#,
+        );
+
+        assert_eq!(rewriter.apply("★  [Video]\n Launch day", &[]), "Launch day 🎥");
+        assert_eq!(rewriter.apply("Plain", &[]), "Plain");
+    }
+
+    #[test]
+    fn test_rewrite_with_named_group() {
+        let rewriter = create_rewriter(
+            r#"
+            title_rewrites = [
+                { regex = "^Episode (?<n>\\d+): (?<title>.*)", replace = "${title} (#${n})" },
+            ]
+            "#,
+        );
+
+        assert_eq!(rewriter.apply("Episode 12: Launch day", &[]), "Launch day (#12)");
+    }
+
+    #[test]
+    fn test_title_rewritten_to_nothing_is_kept() {
+        let rewriter = create_rewriter(r#"title_rewrites = [{ regex = ".*" }]"#);
+
+        assert_eq!(rewriter.apply("Only title", &[]), "Only title");
+    }
+
+    fn create_canonicalizer(links: &str) -> LinkCanonicalizer {
+        let config = Config::from_str(&format!("feeds = []\n[links]\n{}", links)).unwrap();
+        LinkCanonicalizer::compile(&config.links)
+    }
+
+    #[test]
+    fn test_strip_tracking_params() {
+        let links = create_canonicalizer(r#"strip_params = ["utm_*", "fbclid", "ref"]"#);
+
+        assert_eq!(
+            links.apply("https://a.com/post?utm_source=rss&UTM_Medium=feed&id=7&ref=hn"),
+            Some("https://a.com/post?id=7".to_string())
+        );
+        assert_eq!(
+            links.apply("https://a.com/post?fbclid=xyz#comments"),
+            Some("https://a.com/post#comments".to_string())
+        );
+        // Nothing to strip: left exactly as given
+        assert_eq!(links.apply("https://a.com/search?q=a%20b&referrer=x"), None);
+        assert_eq!(links.apply("not a url?utm_source=x"), None);
+    }
+
+    #[test]
+    fn test_domain_rewrites() {
+        let links = create_canonicalizer(
+            r#"
+            [[links.rewrites]]
+            domain = "twitter.com"
+            host = "nitter.example:8443"
+
+            [[links.rewrites]]
+            domain = "Example.com"
+            https = true
+            "#,
+        );
+
+        assert_eq!(
+            links.apply("https://mobile.twitter.com/user/status/1"),
+            Some("https://nitter.example:8443/user/status/1".to_string())
+        );
+        assert_eq!(
+            links.apply("http://blog.example.com/a?b=c"),
+            Some("https://blog.example.com/a?b=c".to_string())
+        );
+        assert_eq!(links.apply("https://example.com/a"), None);
+        assert_eq!(links.apply("http://notexample.com/a"), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Per-feed health and activity figures derived from the fetch log.
+
+use chrono::{DateTime, Utc};
+
+use crate::db::{Feed, FetchRecord};
+
+/// Days of history the statistics page looks at
+pub const STATS_WINDOW_DAYS: i64 = 30;
+
+pub struct FeedStats {
+    pub feed: Feed,
+    pub attempts: usize,
+    pub failures: usize,
+    /// Share of successful fetches, in percent
+    pub uptime_pct: Option<f64>,
+    /// Median duration of successful fetches
+    pub median_latency_ms: Option<i64>,
+    pub posts_per_day: f64,
+    pub last_published: Option<DateTime<Utc>>,
+    /// Entries dropped by include/exclude rules
+    pub filtered_items: i64,
+    /// Dropped entries per rule, filled in by the caller
+    pub filter_counts: Vec<(String, i64)>,
+}
+
+impl FeedStats {
+    pub fn compute(
+        feed: Feed,
+        log: &[FetchRecord],
+        items_in_window: i64,
+        last_published: Option<&str>,
+    ) -> Self {
+        let attempts = log.len();
+        let failures = log.iter().filter(|r| r.error_category.is_some()).count();
+
+        let uptime_pct = if attempts > 0 {
+            Some((attempts - failures) as f64 * 100.0 / attempts as f64)
+        } else {
+            None
+        };
+
+        let mut latencies: Vec<i64> = log
+            .iter()
+            .filter(|r| r.error_category.is_none())
+            .map(|r| r.duration_ms)
+            .collect();
+        latencies.sort_unstable();
+        let median_latency_ms = median(&latencies);
+        let filtered_items = log.iter().map(|r| r.filtered_items).sum();
+
+        let last_published = last_published
+            .and_then(|p| DateTime::parse_from_rfc3339(p).ok())
+            .map(|dt| dt.with_timezone(&Utc));
+
+        Self {
+            feed,
+            attempts,
+            failures,
+            uptime_pct,
+            median_latency_ms,
+            posts_per_day: items_in_window as f64 / STATS_WINDOW_DAYS as f64,
+            last_published,
+            filtered_items,
+            filter_counts: Vec::new(),
+        }
+    }
+
+    pub fn uptime_display(&self) -> String {
+        match self.uptime_pct {
+            Some(pct) => format!("{:.1}%", pct),
+            None => "-".to_string(),
+        }
+    }
+
+    pub fn latency_display(&self) -> String {
+        match self.median_latency_ms {
+            Some(ms) if ms >= 1000 => format!("{:.1}s", ms as f64 / 1000.0),
+            Some(ms) => format!("{}ms", ms),
+            None => "-".to_string(),
+        }
+    }
+
+    pub fn frequency_display(&self) -> String {
+        format!("{:.1}", self.posts_per_day)
+    }
+
+    /// One line per rule, for the tooltip on the filtered count
+    pub fn filter_breakdown(&self) -> String {
+        self.filter_counts
+            .iter()
+            .map(|(rule, count)| format!("{}: {}", rule, count))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn last_published_display(&self) -> String {
+        match self.last_published {
+            Some(dt) => format_age(Utc::now() - dt),
+            None => "never".to_string(),
+        }
+    }
+}
+
+/// Median of an already sorted slice
+fn median(sorted: &[i64]) -> Option<i64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        Some((sorted[mid - 1] + sorted[mid]) / 2)
+    } else {
+        Some(sorted[mid])
+    }
+}
+
+/// Compact age such as "45m", "6h" or "12d"
+pub fn format_age(age: chrono::Duration) -> String {
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_feed() -> Feed {
+        Feed {
+            id: 1,
+            name: "Test".to_string(),
+            url: "https://test.com/rss".to_string(),
+            has_discussion: false,
+            last_fetched: None,
+            last_error: None,
+            homepage_url: None,
+            ..Default::default()
+        }
+    }
+
+    fn create_record(duration_ms: i64, error_category: Option<&str>) -> FetchRecord {
+        FetchRecord {
+            feed_id: 1,
+            started_at: Utc::now().to_rfc3339(),
+            duration_ms,
+            http_status: Some(200),
+            bytes: Some(1000),
+            new_items: 0,
+            updated_items: 0,
+            filtered_items: 0,
+            error_category: error_category.map(|c| c.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_compute_with_no_history() {
+        let stats = FeedStats::compute(create_test_feed(), &[], 0, None);
+
+        assert_eq!(stats.attempts, 0);
+        assert!(stats.uptime_pct.is_none());
+        assert!(stats.median_latency_ms.is_none());
+        assert_eq!(stats.uptime_display(), "-");
+        assert_eq!(stats.last_published_display(), "never");
+    }
+
+    #[test]
+    fn test_uptime_counts_failures() {
+        let log = vec![
+            create_record(100, None),
+            create_record(100, None),
+            create_record(100, None),
+            create_record(30000, Some("network")),
+        ];
+
+        let stats = FeedStats::compute(create_test_feed(), &log, 0, None);
+
+        assert_eq!(stats.attempts, 4);
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.uptime_display(), "75.0%");
+    }
+
+    #[test]
+    fn test_median_latency_ignores_failures() {
+        let log = vec![
+            create_record(300, None),
+            create_record(100, None),
+            create_record(200, None),
+            create_record(30000, Some("network")),
+        ];
+
+        let stats = FeedStats::compute(create_test_feed(), &log, 0, None);
+        assert_eq!(stats.median_latency_ms, Some(200));
+        assert_eq!(stats.latency_display(), "200ms");
+    }
+
+    #[test]
+    fn test_posts_per_day() {
+        let stats = FeedStats::compute(create_test_feed(), &[], 45, None);
+        assert_eq!(stats.frequency_display(), "1.5");
+    }
+
+    #[test]
+    fn test_median_even_count() {
+        assert_eq!(median(&[100, 200, 400, 1000]), Some(300));
+        assert_eq!(median(&[]), None);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(chrono::Duration::minutes(45)), "45m");
+        assert_eq!(format_age(chrono::Duration::hours(6)), "6h");
+        assert_eq!(format_age(chrono::Duration::days(12)), "12d");
+    }
+}
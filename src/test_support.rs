@@ -0,0 +1,95 @@
+//! Fixtures shared by the handler tests of the web, JSON and reader app APIs.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use crate::config::Config;
+use crate::db::{Database, Feed};
+use crate::fetcher::Fetcher;
+use crate::reload::ConfigReloader;
+use crate::routes::AppState;
+use crate::tokens::Signer;
+
+/// Reader app credentials, a group of two feeds and a feed outside it
+pub(crate) const READER_CONFIG: &str = r#"
+    [reader_api]
+    username = "team"
+    password = "hunter2"
+
+    [[groups]]
+    name = "Tech"
+
+    [[feeds]]
+    name = "Ars"
+    url = "https://ars.example.com/rss"
+    group = "Tech"
+
+    [[feeds]]
+    name = "HN"
+    url = "https://hn.example.com/rss"
+    group = "Tech"
+    has_discussion = true
+
+    [[feeds]]
+    name = "NPR"
+    url = "https://npr.example.com/rss"
+"#;
+
+/// App state over an in-memory database, started with `config` applied.
+/// The reloader tracks `config_path`, which is only read on reload; there is no
+/// admin token.
+pub(crate) async fn test_state(config: Config, config_path: impl Into<PathBuf>) -> AppState {
+    let db = Database::new("sqlite::memory:").await.unwrap();
+    db.initialize().await.unwrap();
+    db.sync_feeds(&config.feeds).await.unwrap();
+    let db = Arc::new(db);
+
+    let fetcher = Arc::new(Fetcher::new(db.clone()));
+    fetcher.configure_feeds(&config).await.unwrap();
+    let reloader = Arc::new(ConfigReloader::new(
+        config_path,
+        config,
+        db.clone(),
+        fetcher.clone(),
+    ));
+    AppState {
+        signer: Arc::new(Signer::load(&db).await.unwrap()),
+        db,
+        fetcher,
+        reloader,
+        admin_token: None,
+        base_path: String::new(),
+        trust_forwarded_headers: false,
+    }
+}
+
+/// Items 1 to 3 in Ars, 4 and 5 in HN, 6 in NPR, for the feeds of `READER_CONFIG`.
+/// Published 30, 20, 10, 25 and 5 hours ago; 6 is undated. HN items link to
+/// their discussion.
+pub(crate) async fn seed_reader_items(db: &Database) -> Vec<Feed> {
+    let feeds = db.get_all_feeds().await.unwrap();
+    let entries = [(0, 30), (0, 20), (0, 10), (1, 25), (1, 5)];
+    for (n, (feed, hours)) in entries.into_iter().enumerate() {
+        let published = Utc::now() - chrono::Duration::hours(hours);
+        let link = format!("https://story.example/{}", n + 1);
+        let discussion = (feed == 1).then(|| format!("https://hn.example/item?id={}", n));
+        db.upsert_item(
+            feeds[feed].id,
+            &link,
+            &format!("Story {}", n + 1),
+            &link,
+            None,
+            discussion.as_deref(),
+            Some(published),
+        )
+        .await
+        .unwrap();
+    }
+    db.upsert_item(feeds[2].id, "npr", "Undated", "https://npr.example/a", None, None, None)
+        .await
+        .unwrap();
+    db.update_feed_fetched(feeds[2].id, None, None).await.unwrap();
+    db.get_all_feeds().await.unwrap()
+}
@@ -0,0 +1,109 @@
+//! Signed, expiring tokens for the admin session cookie and reader app sign-in.
+//!
+//! A token is an HMAC over what it was issued for, the secret it stands in for
+//! and its expiry time, under a random key kept in the database. It doesn't give
+//! the secret away, and stops working once it expires or the secret changes.
+
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::db::Database;
+use crate::routes::constant_time_eq;
+
+/// Name of the signing key in the database
+const KEY_NAME: &str = "token_key";
+
+pub struct Signer {
+    key: Vec<u8>,
+}
+
+impl Signer {
+    /// Load the signing key, creating it on first use
+    pub async fn load(db: &Database) -> anyhow::Result<Self> {
+        let key = db
+            .get_or_create_secret(KEY_NAME, || rand::random::<[u8; 32]>().to_vec())
+            .await?;
+        Ok(Self { key })
+    }
+
+    /// A token for `purpose`, tied to `secret` and valid for `lifetime`. It reads
+    /// `<expiry>-<signature>`, with the expiry in Unix seconds and the signature
+    /// in hex, so it is safe in cookies and headers.
+    pub fn issue(&self, purpose: &str, secret: &str, lifetime: Duration) -> String {
+        let expires = (Utc::now() + lifetime).timestamp();
+        format!("{}-{}", expires, self.sign(purpose, secret, expires))
+    }
+
+    /// Whether `token` was issued for `purpose` and `secret` and hasn't expired
+    pub fn verify(&self, purpose: &str, secret: &str, token: &str) -> bool {
+        self.verify_at(purpose, secret, token, Utc::now())
+    }
+
+    fn verify_at(&self, purpose: &str, secret: &str, token: &str, now: DateTime<Utc>) -> bool {
+        let Some((expires, signature)) = token.split_once('-') else {
+            return false;
+        };
+        let Ok(expires) = expires.parse::<i64>() else {
+            return false;
+        };
+        let expected = self.sign(purpose, secret, expires);
+        constant_time_eq(signature.as_bytes(), expected.as_bytes()) && now.timestamp() < expires
+    }
+
+    fn sign(&self, purpose: &str, secret: &str, expires: i64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key size");
+        // Lengths first, so no two inputs run together the same way
+        for part in [purpose.as_bytes(), secret.as_bytes()] {
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part);
+        }
+        mac.update(&expires.to_be_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn create_signer() -> (Signer, Database) {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.initialize().await.unwrap();
+        (Signer::load(&db).await.unwrap(), db)
+    }
+
+    #[tokio::test]
+    async fn test_token_is_bound_to_purpose_secret_and_key() {
+        let (signer, db) = create_signer().await;
+        let token = signer.issue("admin", "s3cret", Duration::hours(1));
+
+        assert!(signer.verify("admin", "s3cret", &token));
+        assert!(!token.contains("s3cret"));
+        assert!(!signer.verify("reader", "s3cret", &token));
+        assert!(!signer.verify("admin", "changed", &token));
+        assert!(!signer.verify("admin", "s3cret", "bogus"));
+        // The key is kept, so tokens outlive a restart
+        assert!(Signer::load(&db).await.unwrap().verify("admin", "s3cret", &token));
+
+        let (other, _db) = create_signer().await;
+        assert!(!other.verify("admin", "s3cret", &token));
+    }
+
+    #[tokio::test]
+    async fn test_token_expires() {
+        let (signer, _db) = create_signer().await;
+        let token = signer.issue("admin", "s3cret", Duration::hours(1));
+
+        let later = Utc::now() + Duration::hours(2);
+        assert!(!signer.verify_at("admin", "s3cret", &token, later));
+        // Moving the expiry breaks the signature
+        let (_, signature) = token.split_once('-').unwrap();
+        let extended = format!("{}-{}", later.timestamp() + 3600, signature);
+        assert!(!signer.verify("admin", "s3cret", &extended));
+    }
+}
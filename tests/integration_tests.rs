@@ -87,7 +87,7 @@ mod database_integration_tests {
     use super::common::*;
     use chrono::Utc;
     use moar_news::config::FeedConfig;
-    use moar_news::db::Database;
+    use moar_news::db::{Database, ItemCursor};
 
     #[tokio::test]
     async fn test_full_database_workflow() {
@@ -104,6 +104,7 @@ mod database_integration_tests {
                 name: "Test Feed".to_string(),
                 url: "https://test.com/rss".to_string(),
                 has_discussion: true,
+                ..Default::default()
             },
         ];
         db.sync_feeds(&configs).await.unwrap();
@@ -122,6 +123,7 @@ mod database_integration_tests {
                 &format!("guid-{}", i),
                 &format!("Article {}", i),
                 &format!("https://article{}.example.com", i),
+                None,
                 Some(&format!("https://discuss{}.example.com", i)),
                 Some(published),
             )
@@ -134,17 +136,25 @@ mod database_integration_tests {
         assert_eq!(count, 25);
 
         // Test pagination - first page
-        let page1 = db.get_items_for_feed(feed.id, 10, 0).await.unwrap();
+        let page1 = db.get_items_for_feed(feed.id, 10, None).await.unwrap();
         assert_eq!(page1.len(), 10);
         assert_eq!(page1[0].title, "Article 25"); // Most recent first
 
         // Test pagination - second page
-        let page2 = db.get_items_for_feed(feed.id, 10, 10).await.unwrap();
+        let cursor = ItemCursor::from_item(page1.last().unwrap());
+        let page2 = db
+            .get_items_for_feed(feed.id, 10, Some(&cursor))
+            .await
+            .unwrap();
         assert_eq!(page2.len(), 10);
         assert_ne!(page1[0].guid, page2[0].guid);
 
         // Test pagination - last page
-        let page3 = db.get_items_for_feed(feed.id, 10, 20).await.unwrap();
+        let cursor = ItemCursor::from_item(page2.last().unwrap());
+        let page3 = db
+            .get_items_for_feed(feed.id, 10, Some(&cursor))
+            .await
+            .unwrap();
         assert_eq!(page3.len(), 5); // Only 5 remaining
 
         // Test update feed fetched
@@ -168,6 +178,7 @@ mod database_integration_tests {
                 name: "Persistent Feed".to_string(),
                 url: "https://persistent.com/rss".to_string(),
                 has_discussion: false,
+                ..Default::default()
             }];
             db.sync_feeds(&configs).await.unwrap();
 
@@ -179,6 +190,7 @@ mod database_integration_tests {
                 "https://persistent.com/article",
                 None,
                 None,
+                None,
             )
             .await
             .unwrap();
@@ -193,7 +205,7 @@ mod database_integration_tests {
             assert_eq!(feeds.len(), 1);
             assert_eq!(feeds[0].name, "Persistent Feed");
 
-            let items = db.get_items_for_feed(feeds[0].id, 10, 0).await.unwrap();
+            let items = db.get_items_for_feed(feeds[0].id, 10, None).await.unwrap();
             assert_eq!(items.len(), 1);
             assert_eq!(items[0].title, "Persistent Article");
         }
@@ -211,6 +223,7 @@ mod database_integration_tests {
             name: "Concurrent Feed".to_string(),
             url: "https://concurrent.com/rss".to_string(),
             has_discussion: false,
+            ..Default::default()
         }];
         db.sync_feeds(&configs).await.unwrap();
         let feeds = db.get_all_feeds().await.unwrap();
@@ -226,6 +239,7 @@ mod database_integration_tests {
                     &format!("https://article{}.com", i),
                     None,
                     None,
+                    None,
                 )
                 .await
                 .unwrap();
@@ -237,7 +251,7 @@ mod database_integration_tests {
         assert_eq!(count, 10);
 
         // All should have "Updated" in title
-        let items = db.get_items_for_feed(feed_id, 10, 0).await.unwrap();
+        let items = db.get_items_for_feed(feed_id, 10, None).await.unwrap();
         for item in items {
             assert!(item.title.contains("Updated"));
         }
@@ -258,6 +272,7 @@ mod fetcher_integration_tests {
             last_fetched: None,
             last_error: None,
             homepage_url: None,
+            ..Default::default()
         }
     }
 
@@ -404,16 +419,19 @@ mod end_to_end_tests {
                 name: "Hacker News".to_string(),
                 url: "https://news.ycombinator.com/rss".to_string(),
                 has_discussion: true,
+                ..Default::default()
             },
             FeedConfig {
                 name: "Lobste.rs".to_string(),
                 url: "https://lobste.rs/rss".to_string(),
                 has_discussion: true,
+                ..Default::default()
             },
             FeedConfig {
                 name: "Ars Technica".to_string(),
                 url: "https://feeds.arstechnica.com/arstechnica/technology-lab".to_string(),
                 has_discussion: false,
+                ..Default::default()
             },
         ];
 
@@ -452,6 +470,7 @@ mod end_to_end_tests {
             name: "Original Name".to_string(),
             url: "https://feed.example.com/rss".to_string(),
             has_discussion: false,
+            ..Default::default()
         }];
         db.sync_feeds(&initial_configs).await.unwrap();
 
@@ -465,6 +484,7 @@ mod end_to_end_tests {
             name: "Updated Name".to_string(),
             url: "https://feed.example.com/rss".to_string(),
             has_discussion: true,
+            ..Default::default()
         }];
         db.sync_feeds(&updated_configs).await.unwrap();
 